@@ -3,7 +3,10 @@
 
 use arduino_hal::{default_serial, delay_ms, pins};
 use cstr_core::cstr;
-use ethernet::{EthernetInitializationMalfunction, EthernetWrapper, IPAddress};
+use ethernet::http::HttpClient;
+use ethernet::{
+    EthernetInitializationMalfunction, EthernetToken, EthernetWrapper, IPAddress, MaintainOutcome,
+};
 use panic_halt as _;
 use rust_arduino_runtime::arduino_main_init;
 use rust_arduino_runtime::ip_address::ip_address_4;
@@ -34,7 +37,8 @@ fn main() -> ! {
 
     let mut mac = [0xde, 0xad, 0xbe, 0xef, 1, 2];
 
-    let ethernet_builder = EthernetWrapper::builder(pins.d10.into_output());
+    let ethernet_builder =
+        EthernetWrapper::builder(EthernetToken::take().unwrap(), pins.d10.into_output());
     let ethernet: EthernetWrapper<_> = match 1 {
         1 => ethernet_builder
             .static_ip_with_dns(&mut mac, fallback_self_ip(), fallback_dns())
@@ -62,7 +66,7 @@ fn main() -> ! {
                                 .static_ip_with_dns(&mut mac, fallback_self_ip(), fallback_dns())
                                 .unwrap()
                         }
-                        EthernetInitializationMalfunction::MissingHardware(_) => {
+                        EthernetInitializationMalfunction::MissingHardware(..) => {
                             let _ = uwriteln!(
                                                     &mut serial,
                                                     "Ethernet shield was not found.  Sorry, can't run without hardware. :("
@@ -92,48 +96,47 @@ fn main() -> ! {
     }
     let _ = uwriteln!(&mut serial, "...");
 
-    let client = ethernet.tcp_connect_hostname(server_name, 80);
-    match client {
-        Err(code) => {
-            let _ = uwriteln!(&mut serial, "connection failed: {}", code);
+    let http = HttpClient::new(&ethernet);
+    let mut head_buffer = [0u8; 256];
+    match http.get(server_name, 80, "/~thoth/art/", &[], &mut head_buffer) {
+        Err(e) => {
+            let _ = uwriteln!(&mut serial, "request failed: {:?}", e);
             spin_forever();
         }
-        Ok(mut client) => {
-            let _ = uwriteln!(&mut serial, "connected to {}", client.remote_ip());
-
-            let _ = uwrite!(
-                &mut client,
-                "GET /~thoth/art/ HTTP/1.1\r
-Host: www.purplefrog.com\r
-Connection: close\r
-\r\n"
-            );
+        Ok(mut response) => {
+            let _ = uwriteln!(&mut serial, "{} {}", response.status(), response.reason());
+            for (name, value) in response.headers() {
+                let _ = uwriteln!(&mut serial, "{}: {}", name, value);
+            }
 
             let mut byte_count: usize = 0;
             let mut buffer = [0u8; 80];
-            while client.connected() {
-                let len = client.available();
-                if len > 0 {
-                    match client.read_multi(&mut buffer) {
-                        Ok(slice) => {
-                            for byte in slice {
-                                serial.write_byte(*byte);
-                            }
-                            serial.flush();
-                            byte_count += slice.len();
-                        }
-                        Err(msg) => {
-                            let _ = uwriteln!(&mut serial, "err {}", msg.msg);
+            loop {
+                match response.read(&mut buffer) {
+                    Ok(0) => break,
+                    Ok(len) => {
+                        for byte in &buffer[..len] {
+                            serial.write_byte(*byte);
                         }
+                        serial.flush();
+                        byte_count += len;
+                    }
+                    Err(e) => {
+                        let _ = uwriteln!(&mut serial, "err {:?}", e);
+                        break;
                     }
                 }
             }
             let _ = uwriteln!(&mut serial, "{} total", byte_count);
+            drop(response); // closes the connection
 
             loop {
                 delay_ms(0x7fff);
-                unsafe {
-                    ethernet::raw::EthernetClass_maintain();
+                match ethernet.maintain() {
+                    MaintainOutcome::Nothing => {}
+                    outcome => {
+                        let _ = uwriteln!(&mut serial, "{:?}", outcome);
+                    }
                 }
             }
         }
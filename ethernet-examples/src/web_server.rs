@@ -2,8 +2,8 @@
 #![no_main]
 
 use arduino_hal::{default_serial, delay_ms, pins, Adc};
-use ethernet::raw::EthernetClient;
-use ethernet::EthernetWrapper;
+use ethernet::http::{Method, Request, Response, ResponseError, Route, Router, Status};
+use ethernet::{EthernetClient, EthernetToken, EthernetWrapper};
 use panic_halt as _;
 use rust_arduino_runtime::arduino_main_init;
 use rust_arduino_runtime::ip_address::ip_address_4;
@@ -16,6 +16,26 @@ where
     ufmt::uwrite!(stream, "analog input {} os {}<br />\n", pin_number, val)
 }
 
+fn analog_page(readings: &mut [u16; 6], _request: &Request, client: &mut EthernetClient) {
+    let _ = send_analog_page(readings, client);
+}
+
+fn send_analog_page(readings: &[u16; 6], client: &mut EthernetClient) -> Result<(), ResponseError> {
+    let mut body = Response::start(client, Status::OK)?
+        .header("Content-Type", "text/html")?
+        .header("Refresh", "5")? // refresh the page automatically every 5 sec
+        .chunked_body()?;
+    ufmt::uwriteln!(&mut body, "<!DOCTYPE HTML>")?;
+    ufmt::uwriteln!(&mut body, "<html>")?;
+
+    for (pin_number, val) in readings.iter().enumerate() {
+        report_analog_pin(*val, pin_number as u8, &mut body)?;
+    }
+
+    ufmt::uwriteln!(&mut body, "</html>")?;
+    Ok(body.finish()?)
+}
+
 #[arduino_hal::entry]
 fn main() -> ! {
     arduino_main_init();
@@ -47,7 +67,8 @@ fn main() -> ! {
     //
 
     let mut mac = [0xde, 0xad, 0xbe, 0xef, 1, 2];
-    let ethernet = match EthernetWrapper::builder(pins.d10.into_output())
+    let token = EthernetToken::take().unwrap();
+    let ethernet = match EthernetWrapper::builder(token, pins.d10.into_output())
         .static_ip(&mut mac, ip_address_4(192, 168, 8, 167))
     {
         Ok(ethernet) => ethernet,
@@ -60,60 +81,37 @@ fn main() -> ! {
     };
     // XXX I really need to figure out the shape of the API I will wrap around hardwareStatus()
 
-    let mut server = ethernet.tcp_listen(80);
+    let mut server = match ethernet.tcp_listen(80) {
+        Ok(server) => server,
+        Err(malfunction) => {
+            let _ = ufmt::uwriteln!(&mut serial, "{:?}; spin forever", malfunction);
+            loop {
+                delay_ms(0x7fff);
+            }
+        }
+    };
     let _ = ufmt::uwriteln!(&mut serial, "server is at {}", ethernet.local_ip());
 
+    const ROUTES: &[Route<[u16; 6]>] = &[Route {
+        method: Some(Method::Get),
+        prefix: "/",
+        handler: analog_page,
+    }];
+    let router = Router::new(ROUTES);
+    let mut buffer = [0u8; 256];
+
     loop {
-        let client: Option<EthernetClient> = server.available_safe();
-        if let Some(mut client) = client {
-            let _ = ufmt::uwriteln!(&mut serial, "new client");
-
-            let mut current_line_is_blank = false;
-            while client.connected() {
-                if client.available() > 0 {
-                    let c = client.read();
-                    match c {
-                        None => break,
-                        Some(c) => {
-                            //let _ = ufmt::uwriteln!(&mut serial, "{} byte", c);
-
-                            // if you've gotten to the end of the line (received a newline
-                            // character) and the line is blank, the HTTP request has ended,
-                            // so you can send a reply
-                            if c == b'\n' && current_line_is_blank {
-                                // send a standard HTTP response header
-                                let _ = ufmt::uwriteln!(&mut client, "HTTP/1.1 200 OK");
-                                let _ = ufmt::uwriteln!(&mut client, "Content-Type: text/html");
-                                let _ = ufmt::uwriteln!(&mut client, "Connection: close"); // the connection will be closed after completion of the response
-                                let _ = ufmt::uwriteln!(&mut client, "Refresh: 5"); // refresh the page automatically every 5 sec
-                                let _ = ufmt::uwriteln!(&mut client, "");
-                                let _ = ufmt::uwriteln!(&mut client, "<!DOCTYPE HTML>");
-                                let _ = ufmt::uwriteln!(&mut client, "<html>");
-
-                                let _ = report_analog_pin(a0.analog_read(&mut adc), 0, &mut client);
-                                let _ = report_analog_pin(a1.analog_read(&mut adc), 1, &mut client);
-                                let _ = report_analog_pin(a2.analog_read(&mut adc), 2, &mut client);
-                                let _ = report_analog_pin(a3.analog_read(&mut adc), 3, &mut client);
-                                let _ = report_analog_pin(a4.analog_read(&mut adc), 4, &mut client);
-                                let _ = report_analog_pin(a5.analog_read(&mut adc), 5, &mut client);
-
-                                //let _ = ufmt::uwriteln!(&mut client, "placeholder");
-                                let _ = ufmt::uwriteln!(&mut client, "</html>");
-                                break;
-                            }
-
-                            if c == b'\n' {
-                                current_line_is_blank = true;
-                            } else if c != b'\r' {
-                                current_line_is_blank = false;
-                            }
-                        }
-                    }
-                }
-            }
-            client.flush();
-            client.stop();
-            let _ = ufmt::uwriteln!(&mut serial, "client disconnected");
+        let mut readings = [
+            a0.analog_read(&mut adc),
+            a1.analog_read(&mut adc),
+            a2.analog_read(&mut adc),
+            a3.analog_read(&mut adc),
+            a4.analog_read(&mut adc),
+            a5.analog_read(&mut adc),
+        ];
+        // parses the request, answers 400/404/414 by itself, and closes the connection after
+        if let Some(outcome) = router.poll(&mut server, &mut buffer, &mut readings) {
+            let _ = ufmt::uwriteln!(&mut serial, "client served: {:?}", outcome);
         }
     }
 }
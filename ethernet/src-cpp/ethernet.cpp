@@ -12,6 +12,43 @@ void virtual_EthernetServer_begin(EthernetServer* that)
      that -> begin();
 }
 
+// there is no EthernetServer::stop(), so close the sockets still listening on the port by hand
+void EthernetServer_close_listeners(uint16_t port)
+{
+    for (uint8_t s = 0; s < MAX_SOCK_NUM; s++) {
+        if (EthernetServer::server_port[s] != port) {
+            continue;
+        }
+        EthernetClient sock(s);
+        if (sock.status() == SnSR::LISTEN) {
+            sock.setConnectionTimeout(0); // nothing to say goodbye to
+            sock.stop();
+            EthernetServer::server_port[s] = 0;
+        }
+    }
+}
+
+size_t virtual_EthernetServer_write(EthernetServer* that, const uint8_t *buf, size_t size)
+{
+    return that->write(buf, size);
+}
+
+// the connections EthernetServer::write would send to
+uint8_t EthernetServer_client_count(uint16_t port)
+{
+    uint8_t count = 0;
+    for (uint8_t s = 0; s < MAX_SOCK_NUM; s++) {
+        if (EthernetServer::server_port[s] != port) {
+            continue;
+        }
+        EthernetClient sock(s);
+        if (sock.status() == SnSR::ESTABLISHED) {
+            count++;
+        }
+    }
+    return count;
+}
+
 //
 
 EthernetClient fabricate_EthernetClient()
@@ -19,9 +56,15 @@ EthernetClient fabricate_EthernetClient()
     return EthernetClient();
 }
 
-int virtual_EthernetClient_connect_hostname(EthernetClient* that, const char *host, uint16_t port)
+// wrap a socket opened with EthernetClass::socketBegin
+EthernetClient fabricate_EthernetClient_socket(uint8_t s)
 {
-    return that->connect(host, port);
+    return EthernetClient(s);
+}
+
+int virtual_EthernetClient_connect_ip(EthernetClient* that, IPAddress ip, uint16_t port)
+{
+    return that->connect(ip, port);
 }
 
 int virtual_EthernetClient_availableForWrite(EthernetClient* that)
@@ -83,3 +126,49 @@ IPAddress virtual_EthernetClient_remoteIP(const EthernetClient *that)
 {
     return ((EthernetClient*)that)->remoteIP();
 }
+
+uint16_t virtual_EthernetClient_remotePort(const EthernetClient *that)
+{
+    return ((EthernetClient*)that)->remotePort();
+}
+
+uint8_t EthernetClient_getSocketNumber(const EthernetClient *that)
+{
+    return that->getSocketNumber();
+}
+
+//
+
+EthernetUDP fabricate_EthernetUDP()
+{
+    return EthernetUDP();
+}
+
+IPAddress virtual_EthernetUDP_remoteIP(EthernetUDP *that)
+{
+    return that->remoteIP();
+}
+
+uint16_t virtual_EthernetUDP_remotePort(EthernetUDP *that)
+{
+    return that->remotePort();
+}
+
+//
+
+// DNSClient's constructor is inline, so do the whole lookup on this side
+int DNSClient_getHostByName(const IPAddress *dns_server, const char *host, IPAddress *result, uint16_t timeout)
+{
+    DNSClient dns;
+    dns.begin(*dns_server);
+    return dns.getHostByName(host, *result, timeout);
+}
+
+//
+
+void IPAddress_octets(const IPAddress *ip, uint8_t *dest)
+{
+    for (int i = 0; i < 4; i++) {
+        dest[i] = (*ip)[i];
+    }
+}
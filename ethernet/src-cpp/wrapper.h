@@ -2,12 +2,17 @@
 #include <SPI.h>
 #include <Ethernet.h>
 #include <Dns.h>
+#include <Dhcp.h>
 
 EthernetServer fabricate_EthernetServer(uint16_t port);
 void virtual_EthernetServer_begin(EthernetServer* that);
+void EthernetServer_close_listeners(uint16_t port);
+size_t virtual_EthernetServer_write(EthernetServer* that, const uint8_t *buf, size_t size);
+uint8_t EthernetServer_client_count(uint16_t port);
 //
 EthernetClient fabricate_EthernetClient();
-int virtual_EthernetClient_connect_hostname(EthernetClient* that, const char *host, uint16_t port);
+EthernetClient fabricate_EthernetClient_socket(uint8_t s);
+int virtual_EthernetClient_connect_ip(EthernetClient* that, IPAddress ip, uint16_t port);
 bool virtual_EthernetClient_connected(EthernetClient* that);
 int virtual_EthernetClient_available(EthernetClient* that);
 size_t virtual_EthernetClient_write(EthernetClient* that, const uint8_t *buf, size_t size);
@@ -21,3 +26,13 @@ int virtual_EthernetClient_availableForWrite(EthernetClient* that);
 Client* cast_to_Client(EthernetClient *that);
 
 IPAddress virtual_EthernetClient_remoteIP(const EthernetClient *that);
+uint16_t virtual_EthernetClient_remotePort(const EthernetClient *that);
+uint8_t EthernetClient_getSocketNumber(const EthernetClient *that);
+//
+EthernetUDP fabricate_EthernetUDP();
+IPAddress virtual_EthernetUDP_remoteIP(EthernetUDP *that);
+uint16_t virtual_EthernetUDP_remotePort(EthernetUDP *that);
+//
+int DNSClient_getHostByName(const IPAddress *dns_server, const char *host, IPAddress *result, uint16_t timeout);
+//
+void IPAddress_octets(const IPAddress *ip, uint8_t *dest);
@@ -0,0 +1,257 @@
+//! `async` sockets, and a tiny executor to run them from the main loop.
+//!
+//! The W5x00 has no interrupt line wired on the usual shields, so there is nothing to wake a task
+//! when a packet arrives.  Instead every future here checks the socket when polled, and if it is
+//! not ready it asks to be polled again (`wake_by_ref`) and returns `Pending`.  [`Executor`]
+//! polls every unfinished task each time round, so a loop of `async` tasks behaves like a
+//! hand-written state machine.  The futures work with other executors too, which will simply
+//! keep them busy-polled.
+//!
+//! ```
+//! let server_task = serve(&mut server);
+//! let mqtt_task = publish(&ethernet);
+//! let pixel_task = async {
+//!     loop {
+//!         strip.step();
+//!         Timer::after_millis(20).await;
+//!     }
+//! };
+//! pin_task!(server_task);
+//! pin_task!(mqtt_task);
+//! pin_task!(pixel_task);
+//! Executor::new([server_task, mqtt_task, pixel_task]).run();
+//!
+//! async fn serve(server: &mut EthernetServer<'_>) {
+//!     let mut buf = [0u8; 64];
+//!     loop {
+//!         let mut client = server.accept_async().await;
+//!         while let Ok(n) = client.read_async(&mut buf).await {
+//!             if n == 0 || client.write_async(&buf[..n]).await.is_err() {
+//!                 break;
+//!             }
+//!         }
+//!     }
+//! }
+//! ```
+
+use crate::{EthernetClient, EthernetServer, EthernetUDP, IPAddress, IoError, UdpError};
+use core::future::Future;
+use core::pin::Pin;
+use core::ptr;
+use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
+
+/// Pin a future to the stack so it can be handed to an [`Executor`]: `pin_task!(task);`
+/// shadows `task` with a `Pin<&mut _>` that can not be moved out from under.
+#[macro_export]
+macro_rules! pin_task {
+    ($name:ident) => {
+        let mut $name = $name;
+        #[allow(unused_mut)]
+        let mut $name = unsafe { core::pin::Pin::new_unchecked(&mut $name) };
+    };
+}
+
+/// not ready yet; ask to be polled again
+fn again<T>(cx: &mut Context<'_>) -> Poll<T> {
+    cx.waker().wake_by_ref();
+    Poll::Pending
+}
+
+impl<'a> EthernetServer<'a> {
+    /// wait for the next new connection, see [`accept`](Self::accept)
+    pub fn accept_async(&mut self) -> AcceptFuture<'_, 'a> {
+        AcceptFuture { server: self }
+    }
+}
+
+/// The future from [`EthernetServer::accept_async`].
+pub struct AcceptFuture<'s, 'a> {
+    server: &'s mut EthernetServer<'a>,
+}
+
+impl<'a> Future for AcceptFuture<'_, 'a> {
+    type Output = EthernetClient<'a>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<EthernetClient<'a>> {
+        match self.server.accept() {
+            Some(client) => Poll::Ready(client),
+            None => again(cx),
+        }
+    }
+}
+
+impl<'a> EthernetClient<'a> {
+    /// Wait for at least one byte.  Like [`embedded_io`](https://docs.rs/embedded-io) `read`,
+    /// this resolves to `Ok(0)` once the peer has closed and everything has been read.
+    pub fn read_async<'c, 'b>(&'c mut self, buf: &'b mut [u8]) -> ReadFuture<'c, 'a, 'b> {
+        ReadFuture { client: self, buf }
+    }
+
+    /// Wait for room in the transmit buffer, then send as much of `buf` as fits.  Resolves to the
+    /// number of bytes sent.
+    pub fn write_async<'c, 'b>(&'c mut self, buf: &'b [u8]) -> WriteFuture<'c, 'a, 'b> {
+        WriteFuture { client: self, buf }
+    }
+}
+
+/// The future from [`EthernetClient::read_async`].
+pub struct ReadFuture<'c, 'a, 'b> {
+    client: &'c mut EthernetClient<'a>,
+    buf: &'b mut [u8],
+}
+
+impl Future for ReadFuture<'_, '_, '_> {
+    type Output = Result<usize, IoError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        if this.buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        match this.client.try_read(this.buf) {
+            Err(IoError::WouldBlock) => again(cx),
+            Err(IoError::PeerClosed) => Poll::Ready(Ok(0)),
+            rval => Poll::Ready(rval),
+        }
+    }
+}
+
+/// The future from [`EthernetClient::write_async`].
+pub struct WriteFuture<'c, 'a, 'b> {
+    client: &'c mut EthernetClient<'a>,
+    buf: &'b [u8],
+}
+
+impl Future for WriteFuture<'_, '_, '_> {
+    type Output = Result<usize, IoError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        if this.buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        match this.client.try_write(this.buf) {
+            Err(IoError::WouldBlock) => again(cx),
+            rval => Poll::Ready(rval),
+        }
+    }
+}
+
+impl<'a> EthernetUDP<'a> {
+    /// wait for the next datagram, see [`recv_from`](Self::recv_from)
+    pub fn recv_from_async<'u, 'b>(&'u mut self, buf: &'b mut [u8]) -> RecvFromFuture<'u, 'a, 'b> {
+        RecvFromFuture { udp: self, buf }
+    }
+}
+
+/// The future from [`EthernetUDP::recv_from_async`].
+pub struct RecvFromFuture<'u, 'a, 'b> {
+    udp: &'u mut EthernetUDP<'a>,
+    buf: &'b mut [u8],
+}
+
+impl Future for RecvFromFuture<'_, '_, '_> {
+    type Output = Result<(usize, IPAddress, u16), UdpError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = &mut *self;
+        match this.udp.recv_from(this.buf) {
+            Err(UdpError::WouldBlock) => again(cx),
+            rval => Poll::Ready(rval),
+        }
+    }
+}
+
+/// Resolves once `millis` milliseconds have passed since it was created.  It reads the Arduino
+/// `millis()` clock, so it survives the wrap-around every 49.7 days.
+pub struct Timer {
+    start: u32,
+    millis: u32,
+}
+
+impl Timer {
+    pub fn after_millis(millis: u32) -> Timer {
+        Timer {
+            start: crate::millis(),
+            millis,
+        }
+    }
+}
+
+impl Future for Timer {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if crate::millis().wrapping_sub(self.start) >= self.millis {
+            Poll::Ready(())
+        } else {
+            again(cx)
+        }
+    }
+}
+
+/// A fixed set of tasks, polled round-robin.  There is no allocation: the tasks live wherever the
+/// caller [pinned](crate::pin_task) them, usually the stack of `main`.
+///
+/// The futures in this module re-wake themselves with `wake_by_ref` whenever they return
+/// `Pending`, and the executor ignores wakes and polls every unfinished task each time round, so
+/// it busy-spins: the CPU never sleeps while a task is waiting for the network or a [`Timer`].
+pub struct Executor<'t, const N: usize> {
+    tasks: [Pin<&'t mut dyn Future<Output = ()>>; N],
+    done: [bool; N],
+}
+
+impl<'t, const N: usize> Executor<'t, N> {
+    pub fn new(tasks: [Pin<&'t mut dyn Future<Output = ()>>; N]) -> Self {
+        Executor {
+            tasks,
+            done: [false; N],
+        }
+    }
+
+    /// Poll every unfinished task once.  Returns `true` once they have all finished.  Call this
+    /// from the main loop if it has other things to do; otherwise use [`run`](Self::run).
+    pub fn poll(&mut self) -> bool {
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        let mut all_done = true;
+        for (task, done) in self.tasks.iter_mut().zip(self.done.iter_mut()) {
+            if !*done {
+                *done = task.as_mut().poll(&mut cx).is_ready();
+                all_done &= *done;
+            }
+        }
+        all_done
+    }
+
+    /// poll until every task has finished, which for most firmware is never
+    pub fn run(&mut self) {
+        while !self.poll() {}
+    }
+}
+
+/// run a single future to completion
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    pin_task!(future);
+    let waker = noop_waker();
+    let mut cx = Context::from_waker(&waker);
+    loop {
+        if let Poll::Ready(rval) = future.as_mut().poll(&mut cx) {
+            return rval;
+        }
+    }
+}
+
+// every task is polled every time round, so waking has nothing to do
+
+static NOOP_VTABLE: RawWakerVTable = RawWakerVTable::new(noop_clone, noop, noop, noop);
+
+fn noop_clone(_: *const ()) -> RawWaker {
+    RawWaker::new(ptr::null(), &NOOP_VTABLE)
+}
+
+fn noop(_: *const ()) {}
+
+fn noop_waker() -> Waker {
+    unsafe { Waker::from_raw(noop_clone(ptr::null())) }
+}
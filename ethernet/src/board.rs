@@ -0,0 +1,51 @@
+use crate::{EthernetBuilder, EthernetToken, EthernetWrapper};
+use avr_hal_generic::port::mode::Output;
+use avr_hal_generic::port::Pin;
+use rust_arduino_helpers::NumberedPin;
+
+/// Where the W5x00 chip-select is wired on the AVR boards this crate runs on.
+///
+/// The pins are in the Arduino core numbering that [`NumberedPin::pin_number`] uses for the
+/// board's pin types, so [`builder_for`](EthernetWrapper::builder_for) can compare them.
+///
+/// Not every board that ships with a W5x00 can be a preset: the MKR ETH shield sits on a SAMD21,
+/// the Ethernet FeatherWing is also sold for the ESP8266 and ESP32 Feathers, and the Teensy++ 2.0
+/// has an AT90USB1286, which avr-hal does not support.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Board {
+    /// the Arduino Ethernet shield and most clones, on an Uno or Mega: D10 (`PB2` on the Uno,
+    /// `PB4` on the Mega)
+    EthernetShield,
+    /// the Adafruit Ethernet FeatherWing on a Feather 32u4: D10 (`PB6`)
+    FeatherWing32u4,
+    /// the ATmega32U4 Teensy 2.0 with the WIZ812 adaptor: `PB0`, which Teensyduino calls pin 0
+    /// but the Leonardo-style numbering of the other 32u4 boards calls D17
+    Teensy2,
+}
+
+impl Board {
+    /// the Arduino pin number wired to the W5x00's chip-select
+    pub fn cs_pin(&self) -> u8 {
+        match self {
+            Board::EthernetShield => 10,
+            Board::FeatherWing32u4 => 10,
+            Board::Teensy2 => 17,
+        }
+    }
+}
+
+impl<P: NumberedPin> EthernetWrapper<P> {
+    /// Like [`builder`](Self::builder), but check that `spi_cs_pin` is the one `board` uses.
+    /// If it is not, you get the pin and token back.
+    pub fn builder_for(
+        board: Board,
+        token: EthernetToken,
+        spi_cs_pin: Pin<Output, P>,
+    ) -> Result<EthernetBuilder<P>, (Pin<Output, P>, EthernetToken)> {
+        if P::pin_number() == board.cs_pin() {
+            Ok(Self::builder(token, spi_cs_pin))
+        } else {
+            Err((spi_cs_pin, token))
+        }
+    }
+}
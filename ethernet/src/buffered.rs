@@ -0,0 +1,311 @@
+//! Buffering in front of the W5x00.  Every [`EthernetClient::read`] is an SPI transaction for a
+//! single byte; [`BufferedClient`] pulls whatever has arrived with one
+//! [`try_read`](EthernetClient::try_read) and hands it out from RAM.  Every write can become its
+//! own TCP segment; [`BufferedWriter`] collects small writes into one.
+
+use crate::{millis, EthernetClient, IoError, LineError, WriteError};
+use core::mem::ManuallyDrop;
+use core::ptr;
+use ufmt::uWrite;
+
+/// how long a [`BufferedClient`] read waits for data unless
+/// [changed](BufferedClient::set_read_timeout)
+pub const DEFAULT_READ_TIMEOUT_MS: u32 = 10_000;
+
+/// An [`EthernetClient`] with an `N` byte receive buffer in front of it.
+///
+/// Every read waits for data, like [`embedded_io`](https://docs.rs/embedded-io) does, and the end
+/// of the stream (the peer closed, and everything was read) shows up as `Ok(0)` or `None`.  When
+/// nothing arrives for the [read timeout](Self::set_read_timeout) it fails with
+/// [`IoError::TimedOut`].
+/// `N` has to be at least 1: a `BufferedClient<0>` does not compile.
+///
+/// ```
+/// let mut reader: BufferedClient<64> = BufferedClient::new(client);
+/// let mut line = [0u8; 80];
+/// loop {
+///     match reader.read_line(&mut line) {
+///         Ok(Some(line)) => handle(line),
+///         Ok(None) => break,
+///         Err(LineError::TooLong) => reader.skip_until(b'\n').map(|_| ())?,
+///         Err(LineError::Io(e)) => return Err(e),
+///     }
+/// }
+/// ```
+pub struct BufferedClient<'a, const N: usize> {
+    client: EthernetClient<'a>,
+    buffer: [u8; N],
+    /// `buffer[start..end]` has been received but not consumed
+    start: usize,
+    end: usize,
+    read_timeout: u32,
+}
+
+impl<'a, const N: usize> BufferedClient<'a, N> {
+    /// fails to evaluate (so `new` fails to compile) when `N` is 0
+    const NOT_EMPTY: () = [()][(N == 0) as usize];
+
+    pub fn new(client: EthernetClient<'a>) -> Self {
+        let () = Self::NOT_EMPTY;
+        BufferedClient {
+            client,
+            buffer: [0; N],
+            start: 0,
+            end: 0,
+            read_timeout: DEFAULT_READ_TIMEOUT_MS,
+        }
+    }
+
+    /// How long a read waits for data before giving up with [`IoError::TimedOut`].  The default
+    /// is [`DEFAULT_READ_TIMEOUT_MS`].
+    pub fn set_read_timeout(&mut self, millis: u32) {
+        self.read_timeout = millis;
+    }
+
+    /// the client, e.g. to write to it
+    pub fn get_mut(&mut self) -> &mut EthernetClient<'a> {
+        &mut self.client
+    }
+
+    /// Unwrap the client.  Anything still in the buffer is lost.
+    pub fn into_inner(self) -> EthernetClient<'a> {
+        self.client
+    }
+
+    /// what has been received but not read yet, without waiting for more
+    pub fn buffered(&self) -> &[u8] {
+        &self.buffer[self.start..self.end]
+    }
+
+    /// The buffered bytes, waiting for more to arrive if there are none.  Empty at the end of the
+    /// stream.  Call [`consume`](Self::consume) with how many were used.
+    pub fn fill_buf(&mut self) -> Result<&[u8], IoError> {
+        if self.start == self.end {
+            self.start = 0;
+            self.end = 0;
+            let start = millis();
+            loop {
+                match self.client.try_read(&mut self.buffer) {
+                    Ok(n) => {
+                        self.end = n;
+                        break;
+                    }
+                    Err(IoError::WouldBlock) => {
+                        if millis().wrapping_sub(start) >= self.read_timeout {
+                            return Err(IoError::TimedOut);
+                        }
+                    }
+                    Err(IoError::PeerClosed) => break,
+                    Err(e) => return Err(e),
+                }
+            }
+        }
+        Ok(self.buffered())
+    }
+
+    /// mark `amount` bytes from [`fill_buf`](Self::fill_buf) as read
+    pub fn consume(&mut self, amount: usize) {
+        self.start = (self.start + amount).min(self.end);
+    }
+
+    /// the next byte, without consuming it; `None` at the end of the stream
+    pub fn peek(&mut self) -> Result<Option<u8>, IoError> {
+        Ok(self.fill_buf()?.first().copied())
+    }
+
+    /// Read whatever has arrived, up to `dest.len()` bytes, waiting for at least one.
+    pub fn read(&mut self, dest: &mut [u8]) -> Result<usize, IoError> {
+        if dest.is_empty() {
+            return Ok(0);
+        }
+        let available = self.fill_buf()?;
+        let n = available.len().min(dest.len());
+        dest[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+
+    /// Copy bytes into `dest` up to and including `delim`, and return how many.  If the stream
+    /// ends first you get the bytes without a `delim`, and `Ok(0)` once there are none.
+    ///
+    /// When `dest` fills up before `delim` arrives this returns [`LineError::TooLong`]; `dest`
+    /// holds the start of the line, and the rest can be read by calling again, or dropped with
+    /// [`skip_until`](Self::skip_until).
+    pub fn read_until(&mut self, delim: u8, dest: &mut [u8]) -> Result<usize, LineError> {
+        let mut len = 0;
+        loop {
+            let available = self.fill_buf()?;
+            if available.is_empty() {
+                return Ok(len);
+            }
+            if len == dest.len() {
+                return Err(LineError::TooLong);
+            }
+            let take = available.len().min(dest.len() - len);
+            let (n, found) = match available[..take].iter().position(|&b| b == delim) {
+                Some(i) => (i + 1, true),
+                None => (take, false),
+            };
+            dest[len..len + n].copy_from_slice(&available[..n]);
+            self.consume(n);
+            len += n;
+            if found {
+                return Ok(len);
+            }
+        }
+    }
+
+    /// Read a line ending in `\n` or `\r\n` into `dest`, and return it without the line ending.
+    /// `None` at the end of the stream.  `dest` needs room for the line ending too; if the line
+    /// does not fit, see [`read_until`](Self::read_until).
+    pub fn read_line<'d>(&mut self, dest: &'d mut [u8]) -> Result<Option<&'d [u8]>, LineError> {
+        let len = self.read_until(b'\n', dest)?;
+        if len == 0 {
+            return Ok(None);
+        }
+        let mut end = len;
+        if dest[end - 1] == b'\n' {
+            end -= 1;
+            if end > 0 && dest[end - 1] == b'\r' {
+                end -= 1;
+            }
+        }
+        Ok(Some(&dest[..end]))
+    }
+
+    /// Throw away everything up to and including `delim`, and return how many bytes that was.
+    /// Stops early at the end of the stream.
+    pub fn skip_until(&mut self, delim: u8) -> Result<usize, IoError> {
+        let mut skipped = 0;
+        loop {
+            let available = self.fill_buf()?;
+            if available.is_empty() {
+                return Ok(skipped);
+            }
+            let (n, found) = match available.iter().position(|&b| b == delim) {
+                Some(i) => (i + 1, true),
+                None => (available.len(), false),
+            };
+            self.consume(n);
+            skipped += n;
+            if found {
+                return Ok(skipped);
+            }
+        }
+    }
+}
+
+/// Collects writes to an [`EthernetClient`] into an `N` byte buffer, so that a `uwriteln!` made
+/// of many fragments goes out as one segment instead of one per fragment.  The buffer is sent
+/// when it fills up, on [`flush`](Self::flush), and when the writer is dropped.
+///
+/// After a write fails every later write fails with the same [`WriteError`], whose `delivered`
+/// counts every byte this writer got to the chip.  `core::fmt::Write` can only return
+/// `fmt::Error`, so call [`flush`](Self::flush) afterwards to find out what went wrong.
+/// `N` has to be at least 1: a `BufferedWriter<0>` does not compile.
+///
+/// ```
+/// let mut out: BufferedWriter<64> = BufferedWriter::new(&mut client);
+/// for (pin, value) in readings.iter().enumerate() {
+///     uwriteln!(&mut out, "analog input {} is {}<br />", pin, value)?;
+/// }
+/// out.flush()?;
+/// ```
+pub struct BufferedWriter<'c, 'a, const N: usize> {
+    client: &'c mut EthernetClient<'a>,
+    buffer: [u8; N],
+    len: usize,
+    delivered: usize,
+    failed: Option<WriteError>,
+}
+
+impl<'c, 'a, const N: usize> BufferedWriter<'c, 'a, N> {
+    /// fails to evaluate (so `new` fails to compile) when `N` is 0
+    const NOT_EMPTY: () = [()][(N == 0) as usize];
+
+    pub fn new(client: &'c mut EthernetClient<'a>) -> Self {
+        let () = Self::NOT_EMPTY;
+        BufferedWriter {
+            client,
+            buffer: [0; N],
+            len: 0,
+            delivered: 0,
+            failed: None,
+        }
+    }
+
+    /// how many bytes have reached the chip so far
+    pub fn delivered(&self) -> usize {
+        self.delivered
+    }
+
+    /// Send everything buffered, then hand the client back.
+    pub fn into_inner(mut self) -> Result<&'c mut EthernetClient<'a>, WriteError> {
+        self.flush()?;
+        let this = ManuallyDrop::new(self);
+        // the buffer is empty, so skipping drop() skips nothing but an empty flush
+        Ok(unsafe { ptr::read(&this.client) })
+    }
+
+    /// Add `data` to the buffer, sending the buffer whenever it fills up.
+    pub fn write(&mut self, mut data: &[u8]) -> Result<(), WriteError> {
+        if let Some(failed) = self.failed {
+            return Err(failed);
+        }
+        while !data.is_empty() {
+            if self.len == N {
+                self.flush()?;
+            }
+            let n = data.len().min(N - self.len);
+            self.buffer[self.len..self.len + n].copy_from_slice(&data[..n]);
+            self.len += n;
+            data = &data[n..];
+        }
+        Ok(())
+    }
+
+    /// Send everything buffered with [`EthernetClient::write_all`].  If the connection fails
+    /// part way, the rest of the buffer is dropped.
+    pub fn flush(&mut self) -> Result<(), WriteError> {
+        if let Some(failed) = self.failed {
+            return Err(failed);
+        }
+        let result = self.client.write_all(&self.buffer[..self.len]);
+        let len = core::mem::replace(&mut self.len, 0);
+        match result {
+            Ok(()) => {
+                self.delivered += len;
+                Ok(())
+            }
+            Err(err) => {
+                self.delivered += err.delivered;
+                let failed = WriteError {
+                    error: err.error,
+                    delivered: self.delivered,
+                };
+                self.failed = Some(failed);
+                Err(failed)
+            }
+        }
+    }
+}
+
+impl<const N: usize> Drop for BufferedWriter<'_, '_, N> {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+impl<const N: usize> uWrite for BufferedWriter<'_, '_, N> {
+    type Error = WriteError;
+
+    fn write_str(&mut self, s: &str) -> Result<(), WriteError> {
+        self.write(s.as_bytes())
+    }
+}
+
+impl<const N: usize> core::fmt::Write for BufferedWriter<'_, '_, N> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.write(s.as_bytes()).map_err(|_| core::fmt::Error)
+    }
+}
@@ -0,0 +1,92 @@
+//! Several connections to one server at once.  [`EthernetServer::accept`] hands each new
+//! connection over exactly once; a [`ClientSet`] keeps them until their peers hang up.
+
+use crate::{EthernetClient, EthernetServer};
+
+/// Up to `N` accepted connections, e.g. every dashboard watching a telemetry server.  Each one
+/// holds a hardware socket, so `N` is at most the [`socket_capacity`](crate::socket_capacity)
+/// minus the server's own.
+///
+/// ```
+/// let mut dashboards: ClientSet<3> = ClientSet::new();
+/// loop {
+///     dashboards.remove_closed();
+///     while dashboards.accept_from(&mut server).is_some() {}
+///     let line = read_sensors();
+///     dashboards.write_all(line.as_bytes());
+/// }
+/// ```
+pub struct ClientSet<'a, const N: usize> {
+    clients: [Option<EthernetClient<'a>>; N],
+}
+
+impl<'a, const N: usize> ClientSet<'a, N> {
+    const EMPTY: Option<EthernetClient<'a>> = None;
+
+    pub fn new() -> Self {
+        ClientSet {
+            clients: [Self::EMPTY; N],
+        }
+    }
+
+    /// Accept the server's next new connection into a free slot.  When the set is full nothing
+    /// is accepted, and new connections wait in the server (where
+    /// [`write_all_clients`](EthernetServer::write_all_clients) still reaches them).
+    pub fn accept_from(
+        &mut self,
+        server: &mut EthernetServer<'a>,
+    ) -> Option<&mut EthernetClient<'a>> {
+        let slot = self.clients.iter_mut().find(|slot| slot.is_none())?;
+        *slot = Some(server.accept()?);
+        slot.as_mut()
+    }
+
+    /// Close the connections whose peers have hung up, once everything they sent has been read.
+    pub fn remove_closed(&mut self) {
+        for slot in self.clients.iter_mut() {
+            if let Some(client) = slot {
+                if !client.connected() {
+                    *slot = None;
+                }
+            }
+        }
+    }
+
+    /// Send `data` to every connection.  A connection the write fails on is closed and removed.
+    /// Returns how many connections it was delivered to.
+    pub fn write_all(&mut self, data: &[u8]) -> usize {
+        let mut delivered = 0;
+        for slot in self.clients.iter_mut() {
+            if let Some(client) = slot {
+                if data.is_empty() || client.write_all(data).is_ok() {
+                    delivered += 1;
+                } else {
+                    *slot = None;
+                }
+            }
+        }
+        delivered
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut EthernetClient<'a>> {
+        self.clients.iter_mut().filter_map(|slot| slot.as_mut())
+    }
+
+    pub fn len(&self) -> usize {
+        self.clients.iter().filter(|slot| slot.is_some()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.len() == N
+    }
+}
+
+impl<const N: usize> Default for ClientSet<'_, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
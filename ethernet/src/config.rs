@@ -0,0 +1,187 @@
+use crate::{raw, EthernetBuilder, EthernetInitializationMalfunction, EthernetWrapper, IPAddress};
+use rust_arduino_helpers::NumberedPin;
+
+/// Everything a static configuration needs.  The Arduino library guesses the gateway and DNS
+/// server as `x.y.z.1` and the subnet as /24 when you leave them out, which is wrong on routed
+/// networks.
+pub struct NetworkConfig {
+    pub ip: IPAddress,
+    pub dns: IPAddress,
+    pub gateway: IPAddress,
+    pub subnet: IPAddress,
+}
+
+impl<P: NumberedPin> EthernetBuilder<P> {
+    /// like [`static_ip_with_dns`](Self::static_ip_with_dns), but with an explicit gateway and subnet mask
+    pub fn static_config(
+        self,
+        mac: &mut [u8; 6],
+        config: NetworkConfig,
+    ) -> Result<EthernetWrapper<P>, EthernetInitializationMalfunction<P>> {
+        unsafe {
+            let mac_ptr: *mut u8 = mac.as_mut_ptr();
+            raw::EthernetClass::begin4(
+                mac_ptr,
+                config.ip,
+                config.dns,
+                config.gateway,
+                config.subnet,
+            )
+        }
+
+        Ok(self.error_if_no_hardware()?.with_pin())
+    }
+}
+
+/// The setters take `&mut self`, so they can not be called while any socket is open.  Changing
+/// the address under an open connection would strand it.
+impl<P: NumberedPin> EthernetWrapper<P> {
+    pub fn gateway_ip(&self) -> IPAddress {
+        unsafe { raw::EthernetClass_gatewayIP() }
+    }
+
+    pub fn subnet_mask(&self) -> IPAddress {
+        unsafe { raw::EthernetClass_subnetMask() }
+    }
+
+    pub fn mac_address(&self) -> [u8; 6] {
+        let mut rval = [0u8; 6];
+        unsafe { raw::EthernetClass::MACAddress(rval.as_mut_ptr()) };
+        rval
+    }
+
+    pub fn set_local_ip(&mut self, ip: IPAddress) {
+        unsafe { ethernet_class().setLocalIP(ip) }
+    }
+
+    pub fn set_mac_address(&mut self, mac: &[u8; 6]) {
+        unsafe { ethernet_class().setMACAddress(mac.as_ptr()) }
+    }
+
+    pub fn set_dns_server_ip(&mut self, dns: IPAddress) {
+        unsafe { raw::EthernetClass__dnsServerAddress = dns } // stupid inline method
+    }
+
+    pub fn set_subnet_mask(&mut self, subnet: IPAddress) {
+        unsafe { ethernet_class().setSubnetMask(subnet) }
+    }
+
+    pub fn set_gateway_ip(&mut self, gateway: IPAddress) {
+        unsafe { ethernet_class().setGatewayIP(gateway) }
+    }
+}
+
+//
+
+/// How long the W5x00 waits for an ACK (or ARP reply) before sending again.  The chip counts in
+/// 100µs steps in a 16 bit register, so the longest possible value is 6553ms.  The power-on
+/// default is 200ms.
+///
+/// After each unanswered retransmission of a TCP segment the chip doubles the wait (until it
+/// would no longer fit the register), and after the [retransmission count](EthernetWrapper::set_retransmission_count)
+/// is used up it gives up and closes the socket.  [`give_up_after`](Self::give_up_after) does
+/// that arithmetic for you.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct RetransmissionTimeout {
+    millis: u16,
+}
+
+impl RetransmissionTimeout {
+    pub const MAX: RetransmissionTimeout = RetransmissionTimeout { millis: 6553 };
+    pub const DEFAULT: RetransmissionTimeout = RetransmissionTimeout { millis: 200 };
+
+    /// values above [`MAX`](Self::MAX) are clamped
+    pub fn from_millis(millis: u16) -> Self {
+        RetransmissionTimeout {
+            millis: millis.min(Self::MAX.millis),
+        }
+    }
+
+    pub fn as_millis(&self) -> u16 {
+        self.millis
+    }
+
+    /// How many milliseconds the chip keeps retrying an unacknowledged TCP segment before it
+    /// gives up, when it is allowed `count` retransmissions (the W5x00 datasheet formula).
+    /// With the defaults (200ms, 8) this is 31.8 seconds.
+    ///
+    /// An ARP request is not backed off, so it gives up after `as_millis() * (count + 1)`.
+    pub fn give_up_after(&self, count: u8) -> u32 {
+        // the register counts in 100µs units
+        let rtr = self.millis as u32 * 10;
+        let mut wait = rtr;
+        let mut total = 0;
+        for _ in 0..=count {
+            total += wait;
+            if wait * 2 <= 0xffff {
+                wait *= 2;
+            }
+        }
+        total / 10
+    }
+}
+
+impl Default for RetransmissionTimeout {
+    fn default() -> Self {
+        RetransmissionTimeout::DEFAULT
+    }
+}
+
+/// the chip's power-on retransmission count
+pub const DEFAULT_RETRANSMISSION_COUNT: u8 = 8;
+
+/// retransmission settings to (re)apply, `None` leaves the chip's default alone
+#[derive(Clone, Copy, Default)]
+pub(crate) struct Retransmission {
+    timeout: Option<RetransmissionTimeout>,
+    count: Option<u8>,
+}
+
+impl Retransmission {
+    /// the chip is reset by `begin`, so these are written after it
+    pub(crate) fn apply(&self) {
+        unsafe {
+            if let Some(timeout) = self.timeout {
+                ethernet_class().setRetransmissionTimeout(timeout.as_millis());
+            }
+            if let Some(count) = self.count {
+                ethernet_class().setRetransmissionCount(count);
+            }
+        }
+    }
+}
+
+impl<P: NumberedPin> EthernetBuilder<P> {
+    /// use `timeout` instead of the chip's 200ms once the stack is up
+    pub fn retransmission_timeout(mut self, timeout: RetransmissionTimeout) -> Self {
+        self.retransmission.timeout = Some(timeout);
+        self
+    }
+
+    /// give up after `count` retransmissions instead of the chip's 8 once the stack is up
+    pub fn retransmission_count(mut self, count: u8) -> Self {
+        self.retransmission.count = Some(count);
+        self
+    }
+}
+
+impl<P: NumberedPin> EthernetWrapper<P> {
+    /// See [`RetransmissionTimeout`] for how this bounds the time
+    /// [`tcp_connect`](Self::tcp_connect) and [`EthernetClient::write`](crate::EthernetClient::write)
+    /// can block on a dead peer.
+    pub fn set_retransmission_timeout(&mut self, timeout: RetransmissionTimeout) {
+        self.retransmission.timeout = Some(timeout);
+        self.retransmission.apply();
+    }
+
+    /// how many times the chip retransmits before giving up, see [`RetransmissionTimeout::give_up_after`]
+    pub fn set_retransmission_count(&mut self, count: u8) {
+        self.retransmission.count = Some(count);
+        self.retransmission.apply();
+    }
+}
+
+/// the global `Ethernet` object, for the setters which are (needlessly) instance methods
+pub(crate) unsafe fn ethernet_class() -> &'static mut raw::EthernetClass {
+    &mut raw::Ethernet
+}
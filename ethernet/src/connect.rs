@@ -0,0 +1,103 @@
+//! Connecting without blocking.  [`EthernetWrapper::tcp_connect`] waits inside the Arduino
+//! library until the handshake is done, which stalls everything else in the main loop.  This
+//! drives the W5x00 socket directly instead: send the SYN, then check on it every time around
+//! the loop.
+
+use crate::{ip_address_octets, raw, ConnectError, EthernetClient, EthernetWrapper, SocketHandle};
+use rust_arduino_helpers::NumberedPin;
+use rust_arduino_runtime::ip_address::IPAddress;
+
+// from w5100.h; bindgen does not generate the static class constants
+/// `SnMR::TCP`
+const SN_MR_TCP: u8 = 0x21;
+/// `SnSR::CLOSED`
+const SN_SR_CLOSED: u8 = 0x00;
+/// `SnSR::ESTABLISHED`
+const SN_SR_ESTABLISHED: u8 = 0x17;
+/// `SnSR::CLOSE_WAIT`
+const SN_SR_CLOSE_WAIT: u8 = 0x1c;
+
+/// What [`PendingConnect::poll`] found.
+pub enum ConnectPoll<'a> {
+    /// the handshake is still going; poll again later
+    Pending,
+    Connected(EthernetClient<'a>),
+    /// refused, or the chip ran out of retransmissions
+    Failed,
+}
+
+/// A TCP connection on its way up.  [`poll`](Self::poll) it from the main loop until it is
+/// [`Connected`](ConnectPoll::Connected) or [`Failed`](ConnectPoll::Failed).  Dropping it
+/// before then abandons the connection.
+///
+/// There is no timeout of its own: the chip retransmits the SYN and gives up after
+/// [`RetransmissionTimeout::give_up_after`](crate::RetransmissionTimeout::give_up_after).
+///
+/// ```
+/// let mut pending = ethernet.tcp_connect_nonblocking(broker_ip, 1883)?;
+/// let client = loop {
+///     match pending.poll() {
+///         ConnectPoll::Pending => animation.step(),
+///         ConnectPoll::Connected(client) => break client,
+///         ConnectPoll::Failed => return Err(ConnectError::Failed),
+///     }
+/// };
+/// ```
+pub struct PendingConnect<'a> {
+    socket: u8,
+    /// `None` once `poll` has handed the socket on (or given up on it)
+    handle: Option<SocketHandle<'a>>,
+}
+
+impl<'a> PendingConnect<'a> {
+    /// Check on the handshake.  Once this returns `Connected` or `Failed`, later calls return
+    /// `Failed`.
+    pub fn poll(&mut self) -> ConnectPoll<'a> {
+        let handle = match self.handle.take() {
+            Some(handle) => handle,
+            None => return ConnectPoll::Failed,
+        };
+        match unsafe { raw::EthernetClass_socketStatus(self.socket) } {
+            SN_SR_ESTABLISHED | SN_SR_CLOSE_WAIT => {
+                ConnectPoll::Connected(EthernetClient::from_socket(self.socket, handle))
+            }
+            SN_SR_CLOSED => ConnectPoll::Failed,
+            _ => {
+                self.handle = Some(handle);
+                ConnectPoll::Pending
+            }
+        }
+    }
+}
+
+impl Drop for PendingConnect<'_> {
+    fn drop(&mut self) {
+        if self.handle.is_some() {
+            unsafe { raw::EthernetClass_socketClose(self.socket) }
+        }
+    }
+}
+
+impl<P: NumberedPin> EthernetWrapper<P> {
+    /// Start a TCP connection to `ip:port` and return without waiting for the handshake.
+    pub fn tcp_connect_nonblocking(
+        &self,
+        ip: IPAddress,
+        port: u16,
+    ) -> Result<PendingConnect<'_>, ConnectError> {
+        let mut octets = ip_address_octets(&ip);
+        if let [0, 0, 0, 0] | [255, 255, 255, 255] = octets {
+            return Err(ConnectError::InvalidAddress);
+        }
+        let handle = SocketHandle::reserve()?;
+        let socket = unsafe { raw::EthernetClass_socketBegin(SN_MR_TCP, 0) };
+        if socket as u32 >= raw::MAX_SOCK_NUM {
+            return Err(ConnectError::NoFreeSocket);
+        }
+        unsafe { raw::EthernetClass_socketConnect(socket, octets.as_mut_ptr(), port) };
+        Ok(PendingConnect {
+            socket,
+            handle: Some(handle),
+        })
+    }
+}
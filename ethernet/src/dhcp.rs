@@ -0,0 +1,96 @@
+use crate::{raw, EthernetWrapper};
+use rust_arduino_helpers::NumberedPin;
+
+/// What [`EthernetWrapper::maintain`] did about the DHCP lease.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaintainOutcome {
+    /// the lease is not due for renewal yet (or the address is static)
+    Nothing,
+    RenewFailed,
+    /// renewed with the original server; the address may have changed
+    RenewSuccess,
+    RebindFailed,
+    /// rebound with any server that would answer; the address may have changed
+    RebindSuccess,
+    Madness(i16),
+}
+
+impl From<i16> for MaintainOutcome {
+    fn from(code: i16) -> Self {
+        match code as u32 {
+            raw::DHCP_CHECK_NONE => MaintainOutcome::Nothing,
+            raw::DHCP_CHECK_RENEW_FAIL => MaintainOutcome::RenewFailed,
+            raw::DHCP_CHECK_RENEW_OK => MaintainOutcome::RenewSuccess,
+            raw::DHCP_CHECK_REBIND_FAIL => MaintainOutcome::RebindFailed,
+            raw::DHCP_CHECK_REBIND_OK => MaintainOutcome::RebindSuccess,
+            _ => MaintainOutcome::Madness(code),
+        }
+    }
+}
+
+impl ufmt::uDebug for MaintainOutcome {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        match self {
+            MaintainOutcome::Nothing => f.write_str("nothing to do"),
+            MaintainOutcome::RenewFailed => f.write_str("DHCP renew failed"),
+            MaintainOutcome::RenewSuccess => f.write_str("DHCP renew succeeded"),
+            MaintainOutcome::RebindFailed => f.write_str("DHCP rebind failed"),
+            MaintainOutcome::RebindSuccess => f.write_str("DHCP rebind succeeded"),
+            MaintainOutcome::Madness(code) => ufmt::uwrite!(f, "DHCP madness {}", code),
+        }
+    }
+}
+
+/// Timers of the current DHCP lease, all in seconds.
+/// They only move forward when [`EthernetWrapper::maintain`] is called.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DhcpLease {
+    /// how long the server granted the lease for
+    pub lease_time: u32,
+    /// until we ask the original server to renew
+    pub renew_in: u32,
+    /// until we ask any server to rebind
+    pub rebind_in: u32,
+    /// until the lease expires
+    pub remaining: u32,
+}
+
+impl ufmt::uDebug for DhcpLease {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        ufmt::uwrite!(
+            f,
+            "lease of {}s, renew in {}s, rebind in {}s, {}s remaining",
+            self.lease_time,
+            self.renew_in,
+            self.rebind_in,
+            self.remaining
+        )
+    }
+}
+
+impl<P: NumberedPin> EthernetWrapper<P> {
+    /// Call this regularly (every few seconds is plenty) so the DHCP lease gets renewed
+    /// before it expires.  Does nothing if the address is static.
+    pub fn maintain(&self) -> MaintainOutcome {
+        unsafe { raw::EthernetClass::maintain() }.into()
+    }
+
+    /// `None` if the address was not assigned by DHCP
+    pub fn lease(&self) -> Option<DhcpLease> {
+        let dhcp = unsafe { raw::EthernetClass__dhcp.as_ref() }?; // the library keeps these private
+        Some(DhcpLease {
+            lease_time: dhcp._dhcpLeaseTime,
+            renew_in: dhcp._renewInSec,
+            rebind_in: dhcp._rebindInSec,
+            remaining: dhcp
+                ._rebindInSec
+                .saturating_add(dhcp._dhcpLeaseTime.saturating_sub(dhcp._dhcpT2)),
+        })
+    }
+}
@@ -0,0 +1,49 @@
+use crate::{raw, DnsError, EthernetWrapper, IPAddress, SocketHandle};
+use cstr_core::CStr;
+use rust_arduino_helpers::NumberedPin;
+use rust_arduino_runtime::ip_address::ip_address_4;
+
+/// how long [`EthernetWrapper::resolve`] waits for each answer, same as the Arduino library
+pub const DEFAULT_DNS_TIMEOUT_MS: u16 = 5000;
+
+impl<P: NumberedPin> EthernetWrapper<P> {
+    /// Look up `host_name` using the DNS server from DHCP (or the one passed to
+    /// [`EthernetBuilder::static_ip_with_dns`](crate::EthernetBuilder::static_ip_with_dns)).
+    /// A dotted quad like `"192.168.8.1"` is parsed without asking the server.
+    pub fn resolve(&self, host_name: &CStr) -> Result<IPAddress, DnsError> {
+        self.resolve_with(host_name, self.dns_server_ip(), DEFAULT_DNS_TIMEOUT_MS)
+    }
+
+    /// Like [`resolve`](Self::resolve), but ask `dns_server` and wait up to `timeout_ms`
+    /// for each of the (up to 3) attempts to read a response.
+    ///
+    /// The query needs a hardware socket for the length of the call; when they are all in use
+    /// this fails with [`DnsError::NoFreeSocket`] (unless `host_name` is a dotted quad).
+    pub fn resolve_with(
+        &self,
+        host_name: &CStr,
+        dns_server: &IPAddress,
+        timeout_ms: u16,
+    ) -> Result<IPAddress, DnsError> {
+        // a dotted quad needs no socket, so only complain if the library failed too
+        let socket = SocketHandle::reserve();
+        let mut rval = ip_address_4(0, 0, 0, 0);
+        let code = unsafe {
+            raw::DNSClient_getHostByName(
+                dns_server as *const IPAddress,
+                host_name.as_ptr(),
+                &mut rval as *mut IPAddress,
+                timeout_ms,
+            )
+        };
+        match code {
+            1 => Ok(rval),
+            _ if socket.is_err() => Err(DnsError::NoFreeSocket),
+            -1 => Err(DnsError::Timeout),
+            -2 => Err(DnsError::InvalidServer),
+            -3 => Err(DnsError::Truncated),
+            -4 => Err(DnsError::InvalidResponse),
+            _ => Err(DnsError::RequestFailed),
+        }
+    }
+}
@@ -0,0 +1,294 @@
+//! Everything that can go wrong in this crate.  Each operation reports its own enum, and [`Error`]
+//! wraps all of them for applications which would rather `?` everything into one type.
+
+use ufmt::{uWrite, Formatter};
+
+/// Every hardware socket is already taken by a client, server or UDP socket.
+/// See [`sockets_in_use`](crate::sockets_in_use).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NoFreeSocket;
+
+impl ufmt::uDebug for NoFreeSocket {
+    fn fmt<W>(&self, f: &mut Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: uWrite + ?Sized,
+    {
+        f.write_str("no free hardware socket")
+    }
+}
+
+/// What went wrong moving bytes through an [`EthernetClient`](crate::EthernetClient).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoError {
+    /// nothing has arrived yet, or the transmit buffer is full.  Try again later.
+    WouldBlock,
+    /// the client never had a hardware socket (it was never connected)
+    NotConnected,
+    /// the peer closed the connection
+    PeerClosed,
+    /// the W5x00 refused the operation
+    Socket,
+    /// the peer did not make room in the transmit buffer, or send anything, in time
+    TimedOut,
+}
+
+impl ufmt::uDebug for IoError {
+    fn fmt<W>(&self, f: &mut Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: uWrite + ?Sized,
+    {
+        f.write_str(match self {
+            IoError::WouldBlock => "would block",
+            IoError::NotConnected => "not connected",
+            IoError::PeerClosed => "peer closed connection",
+            IoError::Socket => "socket error",
+            IoError::TimedOut => "timed out",
+        })
+    }
+}
+
+/// What went wrong sending or receiving with an [`EthernetUDP`](crate::EthernetUDP).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UdpError {
+    /// no datagram is waiting
+    WouldBlock,
+    /// the datagram did not fit in the buffer; only the first `buf.len()` of `packet_len` bytes were kept
+    Truncated { packet_len: usize },
+    /// tried to join a group outside 224.0.0.0/4
+    NotMulticast,
+    /// every hardware socket is already in use
+    NoFreeSocket,
+    /// the library could not open the socket although one was free (e.g. the chip stopped answering)
+    OpenFailed,
+    /// the library rejected the destination address or port
+    InvalidDestination,
+    /// only `written` bytes fit in the transmit buffer; the truncated datagram was still sent
+    PayloadTooLong { written: usize },
+    /// the W5x00 failed to send the datagram
+    SendFailed,
+}
+
+impl From<NoFreeSocket> for UdpError {
+    fn from(_: NoFreeSocket) -> Self {
+        UdpError::NoFreeSocket
+    }
+}
+
+impl ufmt::uDebug for UdpError {
+    fn fmt<W>(&self, f: &mut Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: uWrite + ?Sized,
+    {
+        match self {
+            UdpError::WouldBlock => f.write_str("would block"),
+            UdpError::Truncated { packet_len } => {
+                ufmt::uwrite!(f, "datagram of {} bytes truncated", packet_len)
+            }
+            UdpError::NotMulticast => f.write_str("not a multicast address"),
+            UdpError::NoFreeSocket => f.write_str("no free hardware socket"),
+            UdpError::OpenFailed => f.write_str("could not open socket"),
+            UdpError::InvalidDestination => f.write_str("invalid destination"),
+            UdpError::PayloadTooLong { written } => {
+                ufmt::uwrite!(f, "payload too long, only {} bytes sent", written)
+            }
+            UdpError::SendFailed => f.write_str("send failed"),
+        }
+    }
+}
+
+/// Why [`EthernetWrapper::resolve`](crate::EthernetWrapper::resolve) failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DnsError {
+    /// the server did not answer in time
+    Timeout,
+    /// there is no DNS server configured
+    InvalidServer,
+    /// the answer did not fit in the packet
+    Truncated,
+    /// the answer did not make sense, or did not contain an address
+    InvalidResponse,
+    /// every hardware socket is already in use
+    NoFreeSocket,
+    /// the request could not be sent
+    RequestFailed,
+}
+
+impl ufmt::uDebug for DnsError {
+    fn fmt<W>(&self, f: &mut Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: uWrite + ?Sized,
+    {
+        f.write_str(match self {
+            DnsError::Timeout => "DNS timed out",
+            DnsError::InvalidServer => "invalid DNS server",
+            DnsError::Truncated => "DNS response truncated",
+            DnsError::InvalidResponse => "invalid DNS response",
+            DnsError::NoFreeSocket => "no free hardware socket",
+            DnsError::RequestFailed => "DNS request failed",
+        })
+    }
+}
+
+/// Why [`EthernetWrapper::tcp_connect`](crate::EthernetWrapper::tcp_connect) or
+/// [`EthernetWrapper::tcp_connect_hostname`](crate::EthernetWrapper::tcp_connect_hostname) failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectError {
+    /// the host name could not be resolved
+    Dns(DnsError),
+    /// 0.0.0.0 and 255.255.255.255 can not be connected to
+    InvalidAddress,
+    /// every hardware socket is already in use
+    NoFreeSocket,
+    /// connection refused, or the handshake timed out; the library does not say which
+    Failed,
+}
+
+impl From<DnsError> for ConnectError {
+    fn from(e: DnsError) -> Self {
+        match e {
+            DnsError::NoFreeSocket => ConnectError::NoFreeSocket,
+            e => ConnectError::Dns(e),
+        }
+    }
+}
+
+impl From<NoFreeSocket> for ConnectError {
+    fn from(_: NoFreeSocket) -> Self {
+        ConnectError::NoFreeSocket
+    }
+}
+
+impl ufmt::uDebug for ConnectError {
+    fn fmt<W>(&self, f: &mut Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: uWrite + ?Sized,
+    {
+        match self {
+            ConnectError::Dns(e) => ufmt::uwrite!(f, "{:?}", e),
+            ConnectError::InvalidAddress => f.write_str("invalid address"),
+            ConnectError::NoFreeSocket => f.write_str("no free hardware socket"),
+            ConnectError::Failed => f.write_str("connect failed"),
+        }
+    }
+}
+
+/// What went wrong reading a line from a [`BufferedClient`](crate::BufferedClient).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineError {
+    Io(IoError),
+    /// the line did not fit: the buffer holds its start, and the rest is still waiting to be read
+    TooLong,
+}
+
+impl From<IoError> for LineError {
+    fn from(e: IoError) -> Self {
+        LineError::Io(e)
+    }
+}
+
+impl ufmt::uDebug for LineError {
+    fn fmt<W>(&self, f: &mut Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: uWrite + ?Sized,
+    {
+        match self {
+            LineError::Io(e) => ufmt::uwrite!(f, "{:?}", e),
+            LineError::TooLong => f.write_str("line too long"),
+        }
+    }
+}
+
+/// A write that stopped part way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteError {
+    pub error: IoError,
+    /// how many bytes reached the W5x00 before it failed
+    pub delivered: usize,
+}
+
+/// for code that only reports the [`IoError`]; `delivered` is lost
+impl From<WriteError> for IoError {
+    fn from(e: WriteError) -> Self {
+        e.error
+    }
+}
+
+impl ufmt::uDebug for WriteError {
+    fn fmt<W>(&self, f: &mut Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: uWrite + ?Sized,
+    {
+        ufmt::uwrite!(f, "{:?} after {} bytes", self.error, self.delivered)
+    }
+}
+
+/// Any of the errors above, so an application can `?` them into a single type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    Io(IoError),
+    Udp(UdpError),
+    Dns(DnsError),
+    Connect(ConnectError),
+    Line(LineError),
+    Write(WriteError),
+    NoFreeSocket,
+}
+
+impl From<NoFreeSocket> for Error {
+    fn from(_: NoFreeSocket) -> Self {
+        Error::NoFreeSocket
+    }
+}
+
+impl From<IoError> for Error {
+    fn from(e: IoError) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<UdpError> for Error {
+    fn from(e: UdpError) -> Self {
+        Error::Udp(e)
+    }
+}
+
+impl From<DnsError> for Error {
+    fn from(e: DnsError) -> Self {
+        Error::Dns(e)
+    }
+}
+
+impl From<ConnectError> for Error {
+    fn from(e: ConnectError) -> Self {
+        Error::Connect(e)
+    }
+}
+
+impl From<LineError> for Error {
+    fn from(e: LineError) -> Self {
+        Error::Line(e)
+    }
+}
+
+impl From<WriteError> for Error {
+    fn from(e: WriteError) -> Self {
+        Error::Write(e)
+    }
+}
+
+impl ufmt::uDebug for Error {
+    fn fmt<W>(&self, f: &mut Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: uWrite + ?Sized,
+    {
+        match self {
+            Error::Io(e) => ufmt::uwrite!(f, "{:?}", e),
+            Error::Udp(e) => ufmt::uwrite!(f, "{:?}", e),
+            Error::Dns(e) => ufmt::uwrite!(f, "{:?}", e),
+            Error::Connect(e) => ufmt::uwrite!(f, "{:?}", e),
+            Error::Line(e) => ufmt::uwrite!(f, "{:?}", e),
+            Error::Write(e) => ufmt::uwrite!(f, "{:?}", e),
+            Error::NoFreeSocket => f.write_str("no free hardware socket"),
+        }
+    }
+}
@@ -0,0 +1,580 @@
+use super::head::{next_byte, read_head, utf8, Deadline};
+use super::{ParseError, MAX_HEADERS};
+use crate::{ConnectError, EthernetClient, EthernetWrapper, IoError, WriteError};
+use cstr_core::CStr;
+use rust_arduino_helpers::NumberedPin;
+use ufmt::uWrite;
+
+/// the longest `Location` a redirect can point to
+pub const MAX_LOCATION: usize = 96;
+/// the longest host name a redirect can point to
+pub const MAX_HOST: usize = 48;
+/// how long [`HttpClient`] waits for the server to send something, unless
+/// [changed](HttpClient::read_timeout)
+pub const DEFAULT_READ_TIMEOUT_MS: u32 = 10_000;
+
+/// What went wrong making a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HttpError {
+    Connect(ConnectError),
+    Io(IoError),
+    /// the status line or a header was not HTTP
+    MalformedResponse,
+    /// the status line did not fit in the buffer
+    HeadersTooLong,
+    /// the connection closed before the end of the body
+    Truncated,
+    /// still being redirected after `max_redirects` hops
+    TooManyRedirects,
+    /// the redirect is to `https://`, is relative without a leading `/`, or does not fit
+    /// [`MAX_LOCATION`]/[`MAX_HOST`]
+    UnsupportedRedirect,
+}
+
+impl From<ConnectError> for HttpError {
+    fn from(err: ConnectError) -> Self {
+        HttpError::Connect(err)
+    }
+}
+
+impl From<IoError> for HttpError {
+    fn from(err: IoError) -> Self {
+        HttpError::Io(err)
+    }
+}
+
+impl From<WriteError> for HttpError {
+    fn from(err: WriteError) -> Self {
+        HttpError::Io(err.error)
+    }
+}
+
+impl From<ParseError> for HttpError {
+    fn from(err: ParseError) -> Self {
+        match err {
+            ParseError::BadRequest => HttpError::MalformedResponse,
+            ParseError::UriTooLong => HttpError::HeadersTooLong,
+            ParseError::Disconnected => HttpError::Truncated,
+            ParseError::TimedOut => HttpError::Io(IoError::TimedOut),
+        }
+    }
+}
+
+impl ufmt::uDebug for HttpError {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        match self {
+            HttpError::Connect(err) => err.fmt(f),
+            HttpError::Io(err) => err.fmt(f),
+            HttpError::MalformedResponse => f.write_str("malformed response"),
+            HttpError::HeadersTooLong => f.write_str("status line too long"),
+            HttpError::Truncated => f.write_str("response truncated"),
+            HttpError::TooManyRedirects => f.write_str("too many redirects"),
+            HttpError::UnsupportedRedirect => f.write_str("unsupported redirect"),
+        }
+    }
+}
+
+/// A blocking HTTP/1.1 client.  Every request opens a new connection (`Connection: close`).
+///
+/// ```
+/// let http = HttpClient::new(&ethernet);
+/// let mut buffer = [0u8; 256];
+/// let mut response = http.post(
+///     cstr!("sensors.lan"), 80, "/readings",
+///     &[("Content-Type", "application/json")],
+///     b"{\"a0\":512}",
+///     &mut buffer,
+/// )?;
+/// let mut chunk = [0u8; 32];
+/// while response.read(&mut chunk)? > 0 { /* ... */ }
+/// ```
+pub struct HttpClient<'w, P: NumberedPin> {
+    ethernet: &'w EthernetWrapper<P>,
+    max_redirects: u8,
+    read_timeout: u32,
+}
+
+impl<'w, P: NumberedPin> HttpClient<'w, P> {
+    /// follows up to 3 redirects
+    pub fn new(ethernet: &'w EthernetWrapper<P>) -> Self {
+        HttpClient {
+            ethernet,
+            max_redirects: 3,
+            read_timeout: DEFAULT_READ_TIMEOUT_MS,
+        }
+    }
+
+    /// 0 returns redirects to the caller instead of following them
+    pub fn max_redirects(mut self, max_redirects: u8) -> Self {
+        self.max_redirects = max_redirects;
+        self
+    }
+
+    /// How long to wait for the status line and headers, and for each
+    /// [`ClientResponse::read`], before failing with [`IoError::TimedOut`].  The W5x00 does not
+    /// notice a peer that went away while it is only receiving, so without this a stalled server
+    /// or a pulled cable would wait forever.  The default is [`DEFAULT_READ_TIMEOUT_MS`].
+    pub fn read_timeout(mut self, millis: u32) -> Self {
+        self.read_timeout = millis;
+        self
+    }
+
+    /// `GET path` from `host:port`.  The status line and headers are read into `buffer`.
+    pub fn get<'b>(
+        &self,
+        host: &CStr,
+        port: u16,
+        path: &str,
+        headers: &[(&str, &str)],
+        buffer: &'b mut [u8],
+    ) -> Result<ClientResponse<'w, 'b>, HttpError> {
+        self.request("GET", host, port, path, headers, None, buffer)
+    }
+
+    /// `POST body to path` on `host:port`, with a `Content-Length`.  Pass the `Content-Type` in
+    /// `headers`.  The status line and headers are read into `buffer`.
+    pub fn post<'b>(
+        &self,
+        host: &CStr,
+        port: u16,
+        path: &str,
+        headers: &[(&str, &str)],
+        body: &[u8],
+        buffer: &'b mut [u8],
+    ) -> Result<ClientResponse<'w, 'b>, HttpError> {
+        self.request("POST", host, port, path, headers, Some(body), buffer)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn request<'b>(
+        &self,
+        mut method: &'static str,
+        host: &CStr,
+        port: u16,
+        path: &str,
+        headers: &[(&str, &str)],
+        mut body: Option<&[u8]>,
+        buffer: &'b mut [u8],
+    ) -> Result<ClientResponse<'w, 'b>, HttpError> {
+        let mut redirect = Target::empty();
+        let mut redirected = false;
+        let mut hop = 0;
+        loop {
+            let (host, port, path) = if redirected {
+                redirect.parts()?
+            } else {
+                (host, port, path)
+            };
+
+            let mut client = self.ethernet.tcp_connect_hostname(host, port)?;
+            send_request(&mut client, method, host, port, path, headers, body)?;
+
+            let mut framing = FramingHeaders::default();
+            let mut location = Location::empty();
+            let deadline = Deadline::after(self.read_timeout);
+            let head = read_head(&mut client, &mut *buffer, deadline, &mut |name, value| {
+                framing.observe(name, value);
+                if name.eq_ignore_ascii_case(b"Location") {
+                    location.set(value);
+                }
+            })?;
+            let (status, reason_start) = parse_status_line(&buffer[..head.first_line_end])?;
+
+            if is_redirect(status) && self.max_redirects > 0 {
+                if hop == self.max_redirects {
+                    return Err(HttpError::TooManyRedirects);
+                }
+                let next = Target::parse(location.as_bytes()?, host, port)?;
+                redirect = next;
+                redirected = true;
+                hop += 1;
+                if status == 303 || ((status == 301 || status == 302) && method == "POST") {
+                    // the way browsers do it
+                    method = "GET";
+                    body = None;
+                }
+                continue;
+            }
+
+            let buffer: &'b [u8] = buffer;
+            let body = if status / 100 == 1 || status == 204 || status == 304 {
+                BodyFraming::Length { remaining: 0 }
+            } else if framing.chunked {
+                BodyFraming::Chunked {
+                    remaining: 0,
+                    done: false,
+                }
+            } else if let Some(length) = framing.content_length {
+                BodyFraming::Length { remaining: length }
+            } else {
+                BodyFraming::Close { done: false }
+            };
+            return Ok(ClientResponse {
+                client,
+                status,
+                reason: utf8(&buffer[reason_start..head.first_line_end])?,
+                headers: head.headers(buffer)?,
+                header_count: head.header_count,
+                dropped_headers: head.dropped_headers,
+                body,
+                read_timeout: self.read_timeout,
+            });
+        }
+    }
+}
+
+fn send_request(
+    client: &mut EthernetClient<'_>,
+    method: &str,
+    host: &CStr,
+    port: u16,
+    path: &str,
+    headers: &[(&str, &str)],
+    body: Option<&[u8]>,
+) -> Result<(), IoError> {
+    ufmt::uwrite!(client, "{} {} HTTP/1.1\r\nHost: ", method, path)?;
+    client.write_all(host.to_bytes())?;
+    if port != 80 {
+        ufmt::uwrite!(client, ":{}", port)?;
+    }
+    client.write_str("\r\nConnection: close\r\n")?;
+    for (name, value) in headers {
+        ufmt::uwrite!(client, "{}: {}\r\n", *name, *value)?;
+    }
+    if let Some(body) = body {
+        ufmt::uwrite!(client, "Content-Length: {}\r\n", body.len())?;
+    }
+    client.write_str("\r\n")?;
+    if let Some(body) = body {
+        client.write_all(body)?;
+    }
+    Ok(())
+}
+
+/// `HTTP/1.x SP 3DIGIT SP reason`, giving the status and where the reason starts in `line`.  An
+/// index rather than a slice, so that `line`'s buffer can be read into again after a redirect.
+fn parse_status_line(line: &[u8]) -> Result<(u16, usize), ParseError> {
+    let mut parts = line.splitn(3, |&b| b == b' ');
+    let (version, code) = match (parts.next(), parts.next()) {
+        (Some(version), Some(code)) => (version, code),
+        _ => return Err(ParseError::BadRequest),
+    };
+    if !version.starts_with(b"HTTP/1.") || code.len() != 3 {
+        return Err(ParseError::BadRequest);
+    }
+    let mut status = 0;
+    for digit in code {
+        if !digit.is_ascii_digit() {
+            return Err(ParseError::BadRequest);
+        }
+        status = status * 10 + (digit - b'0') as u16;
+    }
+    let reason_start = (version.len() + 1 + code.len() + 1).min(line.len());
+    Ok((status, reason_start))
+}
+
+fn is_redirect(status: u16) -> bool {
+    matches!(status, 301 | 302 | 303 | 307 | 308)
+}
+
+/// the headers which decide how the body ends, seen even if they are not kept
+#[derive(Default)]
+struct FramingHeaders {
+    content_length: Option<usize>,
+    chunked: bool,
+}
+
+impl FramingHeaders {
+    fn observe(&mut self, name: &[u8], value: &[u8]) {
+        if name.eq_ignore_ascii_case(b"Content-Length") {
+            self.content_length = utf8(value).ok().and_then(|v| v.parse().ok());
+        } else if name.eq_ignore_ascii_case(b"Transfer-Encoding") {
+            // chunked is always the last coding
+            self.chunked =
+                value.len() >= 7 && value[value.len() - 7..].eq_ignore_ascii_case(b"chunked");
+        }
+    }
+}
+
+/// a copy of the `Location` header, which may be overwritten in the buffer
+struct Location {
+    bytes: [u8; MAX_LOCATION],
+    /// `None` if there was no `Location`, or it did not fit
+    len: Option<usize>,
+}
+
+impl Location {
+    fn empty() -> Self {
+        Location {
+            bytes: [0; MAX_LOCATION],
+            len: None,
+        }
+    }
+
+    fn set(&mut self, value: &[u8]) {
+        if value.len() <= MAX_LOCATION {
+            self.bytes[..value.len()].copy_from_slice(value);
+            self.len = Some(value.len());
+        } else {
+            self.len = None;
+        }
+    }
+
+    fn as_bytes(&self) -> Result<&[u8], HttpError> {
+        match self.len {
+            Some(len) => Ok(&self.bytes[..len]),
+            None => Err(HttpError::UnsupportedRedirect),
+        }
+    }
+}
+
+/// where a redirect points; the host is NUL-terminated for the DNS lookup
+struct Target {
+    host: [u8; MAX_HOST + 1],
+    port: u16,
+    path: [u8; MAX_LOCATION],
+    path_len: usize,
+}
+
+impl Target {
+    fn empty() -> Self {
+        Target {
+            host: [0; MAX_HOST + 1],
+            port: 80,
+            path: [0; MAX_LOCATION],
+            path_len: 0,
+        }
+    }
+
+    /// `http://host[:port][/path]` or `/path` (relative to `host:port`)
+    fn parse(location: &[u8], host: &CStr, port: u16) -> Result<Target, HttpError> {
+        let mut rval = Target::empty();
+        let path = if location.starts_with(b"/") {
+            rval.set_host(host.to_bytes())?;
+            rval.port = port;
+            location
+        } else if location.len() >= 7 && location[..7].eq_ignore_ascii_case(b"http://") {
+            let rest = &location[7..];
+            let authority_end = rest.iter().position(|&b| b == b'/').unwrap_or(rest.len());
+            let authority = &rest[..authority_end];
+            let (new_host, new_port) = match authority.iter().position(|&b| b == b':') {
+                Some(colon) => (
+                    &authority[..colon],
+                    utf8(&authority[colon + 1..])
+                        .ok()
+                        .and_then(|p| p.parse().ok())
+                        .ok_or(HttpError::UnsupportedRedirect)?,
+                ),
+                None => (authority, 80),
+            };
+            rval.set_host(new_host)?;
+            rval.port = new_port;
+            match &rest[authority_end..] {
+                b"" => b"/",
+                path => path,
+            }
+        } else {
+            return Err(HttpError::UnsupportedRedirect);
+        };
+        rval.path[..path.len()].copy_from_slice(path);
+        rval.path_len = path.len();
+        Ok(rval)
+    }
+
+    fn set_host(&mut self, host: &[u8]) -> Result<(), HttpError> {
+        if host.is_empty() || host.len() > MAX_HOST || host.contains(&0) {
+            return Err(HttpError::UnsupportedRedirect);
+        }
+        self.host[..host.len()].copy_from_slice(host);
+        self.host[host.len()] = 0;
+        Ok(())
+    }
+
+    fn parts(&self) -> Result<(&CStr, u16, &str), HttpError> {
+        let host_len = self.host.iter().position(|&b| b == 0).unwrap_or(MAX_HOST);
+        let host = CStr::from_bytes_with_nul(&self.host[..=host_len])
+            .map_err(|_| HttpError::UnsupportedRedirect)?;
+        let path = utf8(&self.path[..self.path_len]).map_err(|_| HttpError::UnsupportedRedirect)?;
+        Ok((host, self.port, path))
+    }
+}
+
+enum BodyFraming {
+    Length { remaining: usize },
+    Chunked { remaining: usize, done: bool },
+    Close { done: bool },
+}
+
+/// The status and headers of a response from [`HttpClient`], with the body still to be
+/// [`read`](Self::read).  Dropping it closes the connection.
+pub struct ClientResponse<'a, 'b> {
+    client: EthernetClient<'a>,
+    status: u16,
+    reason: &'b str,
+    headers: [(&'b str, &'b str); MAX_HEADERS],
+    header_count: usize,
+    dropped_headers: usize,
+    body: BodyFraming,
+    read_timeout: u32,
+}
+
+impl<'a, 'b> ClientResponse<'a, 'b> {
+    pub fn status(&self) -> u16 {
+        self.status
+    }
+
+    pub fn reason(&self) -> &'b str {
+        self.reason
+    }
+
+    /// the first header called `name` (ignoring case)
+    pub fn header(&self, name: &str) -> Option<&'b str> {
+        self.headers()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v)
+    }
+
+    /// the headers that were kept, in the order they arrived
+    pub fn headers(&self) -> impl Iterator<Item = (&'b str, &'b str)> + '_ {
+        self.headers[..self.header_count].iter().copied()
+    }
+
+    /// how many headers did not fit
+    pub fn dropped_headers(&self) -> usize {
+        self.dropped_headers
+    }
+
+    /// Read the next part of the body into `dest`, waiting for it to arrive.  Returns 0 at the
+    /// end of the body, however it is framed (`Content-Length`, chunked, or connection close).
+    /// Fails with [`IoError::TimedOut`] if nothing arrives within the
+    /// [read timeout](HttpClient::read_timeout).
+    pub fn read(&mut self, dest: &mut [u8]) -> Result<usize, HttpError> {
+        if dest.is_empty() {
+            return Ok(0);
+        }
+        let deadline = Deadline::after(self.read_timeout);
+        let client = &mut self.client;
+        match &mut self.body {
+            BodyFraming::Length { remaining } => {
+                if *remaining == 0 {
+                    return Ok(0);
+                }
+                let want = dest.len().min(*remaining);
+                let n =
+                    read_some(client, &mut dest[..want], deadline)?.ok_or(HttpError::Truncated)?;
+                *remaining -= n;
+                Ok(n)
+            }
+            BodyFraming::Chunked { remaining, done } => {
+                if *done {
+                    return Ok(0);
+                }
+                if *remaining == 0 {
+                    *remaining = read_chunk_size(client, deadline)?;
+                    if *remaining == 0 {
+                        skip_trailers(client, deadline)?;
+                        *done = true;
+                        return Ok(0);
+                    }
+                }
+                let want = dest.len().min(*remaining);
+                let n =
+                    read_some(client, &mut dest[..want], deadline)?.ok_or(HttpError::Truncated)?;
+                *remaining -= n;
+                if *remaining == 0 {
+                    skip_line(client, deadline)?; // the CRLF after the chunk
+                }
+                Ok(n)
+            }
+            BodyFraming::Close { done } => {
+                if *done {
+                    return Ok(0);
+                }
+                match read_some(client, dest, deadline)? {
+                    Some(n) => Ok(n),
+                    None => {
+                        *done = true;
+                        Ok(0)
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// wait for at least one byte, until `deadline`; `None` once the peer has closed and everything
+/// was read
+fn read_some(
+    client: &mut EthernetClient<'_>,
+    dest: &mut [u8],
+    deadline: Deadline,
+) -> Result<Option<usize>, HttpError> {
+    loop {
+        match client.try_read(dest) {
+            Ok(n) => return Ok(Some(n)),
+            Err(IoError::WouldBlock) if deadline.expired() => {
+                return Err(HttpError::Io(IoError::TimedOut))
+            }
+            Err(IoError::WouldBlock) => continue,
+            Err(IoError::PeerClosed) => return Ok(None),
+            Err(err) => return Err(HttpError::Io(err)),
+        }
+    }
+}
+
+/// `1a;extension=ignored CRLF`
+fn read_chunk_size(
+    client: &mut EthernetClient<'_>,
+    deadline: Deadline,
+) -> Result<usize, HttpError> {
+    let mut size: usize = 0;
+    let mut digits = 0;
+    loop {
+        let b = next_byte(client, deadline)?;
+        let digit = match b {
+            b'0'..=b'9' => b - b'0',
+            b'a'..=b'f' => b - b'a' + 10,
+            b'A'..=b'F' => b - b'A' + 10,
+            b'\n' => break,
+            b';' | b'\r' | b' ' | b'\t' => {
+                skip_line(client, deadline)?;
+                break;
+            }
+            _ => return Err(HttpError::MalformedResponse),
+        };
+        size = size
+            .checked_mul(16)
+            .and_then(|s| s.checked_add(digit as usize))
+            .ok_or(HttpError::MalformedResponse)?;
+        digits += 1;
+    }
+    if digits == 0 {
+        return Err(HttpError::MalformedResponse);
+    }
+    Ok(size)
+}
+
+/// the trailer section after the last chunk, up to the blank line
+fn skip_trailers(client: &mut EthernetClient<'_>, deadline: Deadline) -> Result<(), HttpError> {
+    loop {
+        let mut len = 0;
+        loop {
+            match next_byte(client, deadline)? {
+                b'\n' => break,
+                b'\r' => {}
+                _ => len += 1,
+            }
+        }
+        if len == 0 {
+            return Ok(());
+        }
+    }
+}
+
+fn skip_line(client: &mut EthernetClient<'_>, deadline: Deadline) -> Result<(), HttpError> {
+    while next_byte(client, deadline)? != b'\n' {}
+    Ok(())
+}
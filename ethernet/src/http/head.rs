@@ -0,0 +1,213 @@
+//! Reading the first line and the headers, which requests and responses share.
+
+use super::ParseError;
+use crate::{EthernetClient, IoError};
+use core::str;
+
+/// how many headers a [`Request`](super::Request) or [`ClientResponse`](super::ClientResponse)
+/// keeps; the rest are counted in `dropped_headers()`
+pub const MAX_HEADERS: usize = 8;
+
+/// `start..end` of a header name and of its value in the buffer
+#[derive(Clone, Copy, Default)]
+struct HeaderSpan {
+    name: (usize, usize),
+    value: (usize, usize),
+}
+
+/// When to stop waiting for the peer, on the wrapping `millis()` clock.
+#[derive(Clone, Copy)]
+pub(super) struct Deadline {
+    start: u32,
+    /// `None` waits forever
+    millis: Option<u32>,
+}
+
+impl Deadline {
+    pub(super) const NEVER: Deadline = Deadline {
+        start: 0,
+        millis: None,
+    };
+
+    pub(super) fn after(millis: u32) -> Deadline {
+        Deadline {
+            start: crate::millis(),
+            millis: Some(millis),
+        }
+    }
+
+    pub(super) fn expired(&self) -> bool {
+        match self.millis {
+            Some(millis) => crate::millis().wrapping_sub(self.start) >= millis,
+            None => false,
+        }
+    }
+}
+
+enum Line {
+    /// the line (without CR LF) ends at this index
+    Fits(usize),
+    /// the line did not fit and has been skipped
+    TooLong,
+}
+
+/// Where the first line and the kept headers ended up in the buffer.
+pub(super) struct Head {
+    pub(super) first_line_end: usize,
+    spans: [HeaderSpan; MAX_HEADERS],
+    pub(super) header_count: usize,
+    pub(super) dropped_headers: usize,
+}
+
+impl Head {
+    /// the kept headers as strings, once `buffer` is no longer being written
+    pub(super) fn headers<'b>(
+        &self,
+        buffer: &'b [u8],
+    ) -> Result<[(&'b str, &'b str); MAX_HEADERS], ParseError> {
+        let mut headers = [("", ""); MAX_HEADERS];
+        for (slot, span) in headers.iter_mut().zip(&self.spans[..self.header_count]) {
+            *slot = (
+                utf8(&buffer[span.name.0..span.name.1])?,
+                utf8(&buffer[span.value.0..span.value.1])?,
+            );
+        }
+        Ok(headers)
+    }
+}
+
+/// Read the first line and the headers from `client` into `buffer`, up to and including the
+/// blank line.  This waits for the bytes to arrive, like the Arduino examples do, but gives up
+/// with [`ParseError::TimedOut`] at `deadline`.
+///
+/// A header which does not fit in what is left of `buffer`, or arrives after [`MAX_HEADERS`]
+/// others, is skipped and counted in `dropped_headers`.  `on_header` sees every header that fit
+/// in the buffer, including the ones that were not kept.
+pub(super) fn read_head(
+    client: &mut EthernetClient<'_>,
+    buffer: &mut [u8],
+    deadline: Deadline,
+    on_header: &mut dyn FnMut(&[u8], &[u8]),
+) -> Result<Head, ParseError> {
+    let first_line_end = match read_line(client, buffer, 0, deadline)? {
+        Line::Fits(end) => end,
+        Line::TooLong => return Err(ParseError::UriTooLong),
+    };
+
+    let mut head = Head {
+        first_line_end,
+        spans: [HeaderSpan::default(); MAX_HEADERS],
+        header_count: 0,
+        dropped_headers: 0,
+    };
+    let mut used = first_line_end;
+    loop {
+        let start = used;
+        let end = match read_line(client, buffer, start, deadline)? {
+            Line::Fits(end) => end,
+            Line::TooLong => {
+                head.dropped_headers += 1;
+                continue;
+            }
+        };
+        if end == start {
+            break; // the blank line
+        }
+        let span = parse_header(buffer, start, end)?;
+        on_header(
+            &buffer[span.name.0..span.name.1],
+            &buffer[span.value.0..span.value.1],
+        );
+        if head.header_count < MAX_HEADERS {
+            head.spans[head.header_count] = span;
+            head.header_count += 1;
+            used = end;
+        } else {
+            head.dropped_headers += 1;
+        }
+    }
+    Ok(head)
+}
+
+pub(super) fn utf8(bytes: &[u8]) -> Result<&str, ParseError> {
+    str::from_utf8(bytes).map_err(|_| ParseError::BadRequest)
+}
+
+/// `name: value`, with the whitespace around the value trimmed
+fn parse_header(buffer: &[u8], start: usize, end: usize) -> Result<HeaderSpan, ParseError> {
+    let line = &buffer[start..end];
+    let colon = match line.iter().position(|&b| b == b':') {
+        Some(colon) if colon > 0 => colon,
+        _ => return Err(ParseError::BadRequest),
+    };
+    // also rejects obsolete line folding, which starts with whitespace
+    if line[..colon].iter().any(|b| b.is_ascii_whitespace()) {
+        return Err(ParseError::BadRequest);
+    }
+    let mut value_start = start + colon + 1;
+    let mut value_end = end;
+    while value_start < value_end && is_ows(buffer[value_start]) {
+        value_start += 1;
+    }
+    while value_end > value_start && is_ows(buffer[value_end - 1]) {
+        value_end -= 1;
+    }
+    Ok(HeaderSpan {
+        name: (start, start + colon),
+        value: (value_start, value_end),
+    })
+}
+
+fn is_ows(b: u8) -> bool {
+    b == b' ' || b == b'\t'
+}
+
+/// Read one line into `buffer[start..]`.  Bytes that do not fit are read and thrown away.
+fn read_line(
+    client: &mut EthernetClient<'_>,
+    buffer: &mut [u8],
+    start: usize,
+    deadline: Deadline,
+) -> Result<Line, ParseError> {
+    let mut end = start;
+    let mut len = 0;
+    let mut last = 0;
+    loop {
+        let b = next_byte(client, deadline)?;
+        if b == b'\n' {
+            break;
+        }
+        if end < buffer.len() {
+            buffer[end] = b;
+            end += 1;
+        }
+        len += 1;
+        last = b;
+    }
+    if len == 0 || (len == 1 && last == b'\r') {
+        // the blank line has to be recognized even when the buffer is full
+        return Ok(Line::Fits(start));
+    }
+    // the `\r` does not have to fit, only what comes before it
+    let content = if last == b'\r' { len - 1 } else { len };
+    if end - start < content {
+        return Ok(Line::TooLong);
+    }
+    Ok(Line::Fits(start + content))
+}
+
+/// wait for the next byte, until `deadline`
+pub(super) fn next_byte(
+    client: &mut EthernetClient<'_>,
+    deadline: Deadline,
+) -> Result<u8, ParseError> {
+    let mut b = [0u8];
+    loop {
+        match client.try_read(&mut b) {
+            Ok(_) => return Ok(b[0]),
+            Err(IoError::WouldBlock) if deadline.expired() => return Err(ParseError::TimedOut),
+            Err(IoError::WouldBlock) => continue,
+            Err(_) => return Err(ParseError::Disconnected),
+        }
+    }
+}
@@ -0,0 +1,99 @@
+//! A small HTTP/1.1 server for the W5x00, without allocation.
+//!
+//! [`Router`] takes connections from an [`EthernetServer`](crate::EthernetServer), parses the
+//! request line and headers into a caller-supplied buffer, and hands the [`Request`] to the
+//! first [`Route`] whose method and path prefix match.  Bad requests get `400`, request lines
+//! longer than the buffer get `414` and unrouted paths get `404`.  There is no keep-alive: the
+//! connection is closed after every request.
+//!
+//! Handlers answer with a [`Response`], which keeps the status line, headers and body in order.
+//!
+//! [`HttpClient`] goes the other way: it sends `GET` and `POST` requests, follows a bounded number
+//! of redirects, and streams the body of the [`ClientResponse`] whatever its framing.
+
+mod client;
+mod head;
+mod request;
+mod response;
+mod server;
+
+pub use client::{
+    ClientResponse, HttpClient, HttpError, DEFAULT_READ_TIMEOUT_MS, MAX_HOST, MAX_LOCATION,
+};
+pub use head::MAX_HEADERS;
+pub use request::Request;
+pub use response::{Response, ResponseBody, ResponseError, Status};
+pub use server::{write_status, Handler, Outcome, Route, Router, DEFAULT_REQUEST_TIMEOUT_MS};
+
+/// The request method.  Anything else is [`Method::Other`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Method {
+    Get,
+    Head,
+    Post,
+    Put,
+    Delete,
+    Options,
+    Patch,
+    Other,
+}
+
+impl Method {
+    fn parse(token: &[u8]) -> Method {
+        match token {
+            b"GET" => Method::Get,
+            b"HEAD" => Method::Head,
+            b"POST" => Method::Post,
+            b"PUT" => Method::Put,
+            b"DELETE" => Method::Delete,
+            b"OPTIONS" => Method::Options,
+            b"PATCH" => Method::Patch,
+            _ => Method::Other,
+        }
+    }
+}
+
+impl ufmt::uDebug for Method {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        f.write_str(match self {
+            Method::Get => "GET",
+            Method::Head => "HEAD",
+            Method::Post => "POST",
+            Method::Put => "PUT",
+            Method::Delete => "DELETE",
+            Method::Options => "OPTIONS",
+            Method::Patch => "PATCH",
+            Method::Other => "other",
+        })
+    }
+}
+
+/// Why a request could not be parsed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseError {
+    /// malformed request line or header, or not UTF-8 (`400`)
+    BadRequest,
+    /// the request line did not fit in the buffer (`414`)
+    UriTooLong,
+    /// the peer went away before the end of the headers
+    Disconnected,
+    /// the peer went quiet before the end of the headers
+    TimedOut,
+}
+
+impl ufmt::uDebug for ParseError {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        f.write_str(match self {
+            ParseError::BadRequest => "bad request",
+            ParseError::UriTooLong => "URI too long",
+            ParseError::Disconnected => "peer disconnected",
+            ParseError::TimedOut => "timed out",
+        })
+    }
+}
@@ -0,0 +1,127 @@
+use super::head::{read_head, utf8, Deadline};
+use super::{Method, ParseError, MAX_HEADERS};
+use crate::EthernetClient;
+
+/// The request line and headers of one HTTP request, borrowed from the buffer they were read into.
+/// The body (if any) is still waiting in the [`EthernetClient`].
+pub struct Request<'b> {
+    method: Method,
+    path: &'b str,
+    query: Option<&'b str>,
+    headers: [(&'b str, &'b str); MAX_HEADERS],
+    header_count: usize,
+    dropped_headers: usize,
+}
+
+impl<'b> Request<'b> {
+    /// Read the request line and headers from `client` into `buffer`, up to and including the
+    /// blank line.  This waits for the bytes to arrive, like the Arduino examples do.
+    ///
+    /// A header which does not fit in what is left of `buffer`, or arrives after
+    /// [`MAX_HEADERS`] others, is skipped and counted in [`dropped_headers`](Self::dropped_headers).
+    pub fn read_from(
+        client: &mut EthernetClient<'_>,
+        buffer: &'b mut [u8],
+    ) -> Result<Request<'b>, ParseError> {
+        Self::read_head(client, buffer, Deadline::NEVER)
+    }
+
+    /// Like [`read_from`](Self::read_from), but gives up with [`ParseError::TimedOut`] if the
+    /// whole head has not arrived within `millis` milliseconds.
+    pub fn read_within(
+        client: &mut EthernetClient<'_>,
+        buffer: &'b mut [u8],
+        millis: u32,
+    ) -> Result<Request<'b>, ParseError> {
+        Self::read_head(client, buffer, Deadline::after(millis))
+    }
+
+    fn read_head(
+        client: &mut EthernetClient<'_>,
+        buffer: &'b mut [u8],
+        deadline: Deadline,
+    ) -> Result<Request<'b>, ParseError> {
+        let head = read_head(client, buffer, deadline, &mut |_, _| {})?;
+
+        let buffer: &'b [u8] = buffer;
+        let (method, target) = parse_request_line(&buffer[..head.first_line_end])?;
+        let target = utf8(target)?;
+        let (path, query) = match target.find('?') {
+            Some(i) => (&target[..i], Some(&target[i + 1..])),
+            None => (target, None),
+        };
+
+        Ok(Request {
+            method,
+            path,
+            query,
+            headers: head.headers(buffer)?,
+            header_count: head.header_count,
+            dropped_headers: head.dropped_headers,
+        })
+    }
+
+    pub fn method(&self) -> Method {
+        self.method
+    }
+
+    /// the request target up to the `?`, e.g. `/status`
+    pub fn path(&self) -> &'b str {
+        self.path
+    }
+
+    /// everything after the `?`, still percent-encoded
+    pub fn query(&self) -> Option<&'b str> {
+        self.query
+    }
+
+    /// the value of `name=value` in the query string, still percent-encoded
+    pub fn query_param(&self, name: &str) -> Option<&'b str> {
+        self.query?.split('&').find_map(|pair| {
+            let mut parts = pair.splitn(2, '=');
+            if parts.next() == Some(name) {
+                Some(parts.next().unwrap_or(""))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// the first header called `name` (ignoring case)
+    pub fn header(&self, name: &str) -> Option<&'b str> {
+        self.headers()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v)
+    }
+
+    /// the headers that were kept, in the order they arrived
+    pub fn headers(&self) -> impl Iterator<Item = (&'b str, &'b str)> + '_ {
+        self.headers[..self.header_count].iter().copied()
+    }
+
+    /// how many headers did not fit
+    pub fn dropped_headers(&self) -> usize {
+        self.dropped_headers
+    }
+
+    /// the `Content-Length` header, if there is a valid one
+    pub fn content_length(&self) -> Option<usize> {
+        self.header("Content-Length")?.parse().ok()
+    }
+}
+
+/// `METHOD SP request-target SP HTTP/1.x`
+fn parse_request_line(line: &[u8]) -> Result<(Method, &[u8]), ParseError> {
+    let mut parts = line.split(|&b| b == b' ');
+    let (method, target, version) = match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(method), Some(target), Some(version), None) => (method, target, version),
+        _ => return Err(ParseError::BadRequest),
+    };
+    if method.is_empty() || !version.starts_with(b"HTTP/1.") {
+        return Err(ParseError::BadRequest);
+    }
+    match target.first() {
+        Some(b'/') | Some(b'*') => Ok((Method::parse(method), target)),
+        _ => Err(ParseError::BadRequest),
+    }
+}
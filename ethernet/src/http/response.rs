@@ -0,0 +1,288 @@
+use crate::{BufferedWriter, EthernetClient, IoError, WriteError};
+use ufmt::uWrite;
+
+/// A status code and its reason phrase, for [`Response::start`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Status {
+    pub code: u16,
+    pub reason: &'static str,
+}
+
+impl Status {
+    pub const OK: Status = Status::new(200, "OK");
+    pub const NO_CONTENT: Status = Status::new(204, "No Content");
+    pub const BAD_REQUEST: Status = Status::new(400, "Bad Request");
+    pub const NOT_FOUND: Status = Status::new(404, "Not Found");
+    pub const METHOD_NOT_ALLOWED: Status = Status::new(405, "Method Not Allowed");
+    pub const REQUEST_TIMEOUT: Status = Status::new(408, "Request Timeout");
+    pub const URI_TOO_LONG: Status = Status::new(414, "URI Too Long");
+    pub const INTERNAL_SERVER_ERROR: Status = Status::new(500, "Internal Server Error");
+    pub const SERVICE_UNAVAILABLE: Status = Status::new(503, "Service Unavailable");
+
+    pub const fn new(code: u16, reason: &'static str) -> Self {
+        Status { code, reason }
+    }
+}
+
+/// What went wrong writing a response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseError {
+    Io(IoError),
+    /// more body than the `Content-Length` promised; the excess was not sent
+    BodyTooLong,
+}
+
+impl From<IoError> for ResponseError {
+    fn from(err: IoError) -> Self {
+        ResponseError::Io(err)
+    }
+}
+
+impl From<WriteError> for ResponseError {
+    fn from(err: WriteError) -> Self {
+        ResponseError::Io(err.error)
+    }
+}
+
+impl ufmt::uDebug for ResponseError {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        match self {
+            ResponseError::Io(err) => err.fmt(f),
+            ResponseError::BodyTooLong => f.write_str("body longer than Content-Length"),
+        }
+    }
+}
+
+/// The header part of a response.  Add headers, then pick how the body is framed with
+/// [`body`](Self::body) or [`chunked_body`](Self::chunked_body).  The status line and headers
+/// are collected in a [`BufferedWriter`] and only sent when the body starts (or earlier, if they
+/// outgrow it), so the head does not go out one segment per fragment.
+///
+/// Every response carries `Connection: close`, because the server closes the connection after
+/// each request.  Dropping a `Response` without a body ends it with `Content-Length: 0`.
+///
+/// ```
+/// let mut body = Response::start(client, Status::OK)?
+///     .header("Content-Type", "text/plain")?
+///     .chunked_body()?;
+/// for reading in readings {
+///     uwriteln!(&mut body, "{}", reading)?;
+/// }
+/// body.finish()?;
+/// ```
+pub struct Response<'c, 'a> {
+    /// `None` once the response has moved on to the body
+    head: Option<BufferedWriter<'c, 'a, HEAD_BUFFER>>,
+}
+
+/// enough for the status line, `Connection: close` and a short header or two
+const HEAD_BUFFER: usize = 64;
+
+impl<'c, 'a> Response<'c, 'a> {
+    /// start with the status line
+    pub fn start(client: &'c mut EthernetClient<'a>, status: Status) -> Result<Self, IoError> {
+        let mut head = BufferedWriter::new(client);
+        ufmt::uwrite!(
+            &mut head,
+            "HTTP/1.1 {} {}\r\nConnection: close\r\n",
+            status.code,
+            status.reason
+        )?;
+        Ok(Response { head: Some(head) })
+    }
+
+    fn head(&mut self) -> &mut BufferedWriter<'c, 'a, HEAD_BUFFER> {
+        self.head.as_mut().unwrap()
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Result<Self, IoError> {
+        ufmt::uwrite!(self.head(), "{}: {}\r\n", name, value)?;
+        Ok(self)
+    }
+
+    /// Send `Content-Length: length` and end the headers.  Exactly `length` bytes of body should follow.
+    pub fn body(mut self, length: usize) -> Result<ResponseBody<'c, 'a>, IoError> {
+        ufmt::uwrite!(self.head(), "Content-Length: {}\r\n\r\n", length)?;
+        Ok(ResponseBody {
+            client: Some(self.send_head()?),
+            framing: Framing::Length { remaining: length },
+        })
+    }
+
+    /// End the headers and send the body in chunks, for when the length is not known up front.
+    pub fn chunked_body(mut self) -> Result<ResponseBody<'c, 'a>, IoError> {
+        self.head().write(b"Transfer-Encoding: chunked\r\n\r\n")?;
+        Ok(ResponseBody {
+            client: Some(self.send_head()?),
+            framing: Framing::Chunked {
+                buffer: [0; CHUNK_BUFFER],
+                len: 0,
+            },
+        })
+    }
+
+    /// send what is left of the head and hand the client on to the body
+    fn send_head(&mut self) -> Result<&'c mut EthernetClient<'a>, IoError> {
+        Ok(self.head.take().unwrap().into_inner()?)
+    }
+
+    /// end the response without a body
+    pub fn finish(mut self) -> Result<(), IoError> {
+        self.end_without_body()
+    }
+
+    fn end_without_body(&mut self) -> Result<(), IoError> {
+        match self.head.take() {
+            Some(mut head) => {
+                head.write(b"Content-Length: 0\r\n\r\n")?;
+                Ok(head.flush()?)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Response<'_, '_> {
+    fn drop(&mut self) {
+        let _ = self.end_without_body();
+    }
+}
+
+/// the most body a chunk carries
+const CHUNK_DATA: usize = 64;
+/// room for `40\r\n`, the data, `\r\n`, and the last chunk `0\r\n\r\n`
+const CHUNK_BUFFER: usize = 4 + CHUNK_DATA + 2 + 5;
+
+enum Framing {
+    Length {
+        remaining: usize,
+    },
+    /// The body is collected into chunks of up to [`CHUNK_DATA`] bytes, each sent with one
+    /// write, so that `uwrite!` fragments do not each become a chunk (and a segment) of their own.
+    /// The data lives at `buffer[4..4 + len]`, leaving room in front for the size line.
+    Chunked {
+        buffer: [u8; CHUNK_BUFFER],
+        len: usize,
+    },
+}
+
+/// The body of a [`Response`].  Write to it with [`write`](Self::write) or `uwrite!`, then
+/// [`finish`](Self::finish) it, or just drop it.  A chunked body is buffered, and only goes out
+/// in full chunks and when it is finished.
+pub struct ResponseBody<'c, 'a> {
+    /// `None` once finished
+    client: Option<&'c mut EthernetClient<'a>>,
+    framing: Framing,
+}
+
+impl ResponseBody<'_, '_> {
+    pub fn write(&mut self, data: &[u8]) -> Result<(), ResponseError> {
+        let client = match &mut self.client {
+            Some(client) => client,
+            None => return Ok(()),
+        };
+        if data.is_empty() {
+            // an empty chunk would end a chunked body
+            return Ok(());
+        }
+        match &mut self.framing {
+            Framing::Length { remaining } => {
+                let n = data.len().min(*remaining);
+                if n > 0 {
+                    client.write_all(&data[..n])?;
+                    *remaining -= n;
+                }
+                if n < data.len() {
+                    return Err(ResponseError::BodyTooLong);
+                }
+            }
+            Framing::Chunked { buffer, len } => {
+                let mut data = data;
+                while !data.is_empty() {
+                    if *len == CHUNK_DATA {
+                        send_chunk(client, buffer, len, false)?;
+                    }
+                    let n = data.len().min(CHUNK_DATA - *len);
+                    buffer[4 + *len..4 + *len + n].copy_from_slice(&data[..n]);
+                    *len += n;
+                    data = &data[n..];
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Send the last chunk of a chunked body.  A `Content-Length` body has nothing more to send;
+    /// if it came up short, the client will notice when the connection closes.
+    pub fn finish(mut self) -> Result<(), IoError> {
+        self.end()
+    }
+
+    fn end(&mut self) -> Result<(), IoError> {
+        match (self.client.take(), &mut self.framing) {
+            (Some(client), Framing::Chunked { buffer, len }) => {
+                send_chunk(client, buffer, len, true)
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Drop for ResponseBody<'_, '_> {
+    fn drop(&mut self) {
+        let _ = self.end();
+    }
+}
+
+impl uWrite for ResponseBody<'_, '_> {
+    type Error = ResponseError;
+
+    fn write_str(&mut self, s: &str) -> Result<(), ResponseError> {
+        self.write(s.as_bytes())
+    }
+}
+
+/// Send the `len` bytes at `buffer[4..]` as one chunk, followed by the last chunk if `last`, in
+/// a single write.
+fn send_chunk(
+    client: &mut EthernetClient<'_>,
+    buffer: &mut [u8; CHUNK_BUFFER],
+    len: &mut usize,
+    last: bool,
+) -> Result<(), IoError> {
+    let mut start = 4;
+    let mut end = 4;
+    if *len > 0 {
+        let mut hex = [0u8; 2];
+        let hex = format_hex(*len, &mut hex);
+        start -= hex.len() + 2;
+        buffer[start..start + hex.len()].copy_from_slice(hex);
+        buffer[4 - 2..4].copy_from_slice(b"\r\n");
+        end += *len;
+        buffer[end..end + 2].copy_from_slice(b"\r\n");
+        end += 2;
+    }
+    if last {
+        buffer[end..end + 5].copy_from_slice(b"0\r\n\r\n");
+        end += 5;
+    }
+    *len = 0;
+    Ok(client.write_all(&buffer[start..end])?)
+}
+
+/// `n` in lowercase hex, without leading zeros
+fn format_hex(mut n: usize, buf: &mut [u8]) -> &[u8] {
+    let mut start = buf.len();
+    loop {
+        start -= 1;
+        buf[start] = b"0123456789abcdef"[n & 0xf];
+        n >>= 4;
+        if n == 0 {
+            break;
+        }
+    }
+    &buf[start..]
+}
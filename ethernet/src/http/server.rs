@@ -0,0 +1,148 @@
+use super::{Method, ParseError, Request, Response, Status};
+use crate::{EthernetClient, EthernetServer, IoError};
+
+/// how long [`Router`] waits for a request's headers, unless [changed](Router::timeout)
+pub const DEFAULT_REQUEST_TIMEOUT_MS: u32 = 5000;
+
+/// Handles a routed request.  `C` is whatever state the handlers share (sensors, counters, ...).
+/// The handler writes the whole response to the client (see [`Response`]); the router closes the
+/// connection after.
+pub type Handler<C> = fn(&mut C, &Request<'_>, &mut EthernetClient<'_>);
+
+/// Send requests for `method` (or any method, for `None`) whose path starts with `prefix` to `handler`.
+pub struct Route<C> {
+    pub method: Option<Method>,
+    pub prefix: &'static str,
+    pub handler: Handler<C>,
+}
+
+impl<C> Route<C> {
+    fn matches(&self, request: &Request<'_>) -> bool {
+        let method_ok = match self.method {
+            Some(method) => method == request.method(),
+            None => true,
+        };
+        method_ok && request.path().starts_with(self.prefix)
+    }
+}
+
+/// What happened to the connection [`Router::poll`] picked up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// a route handled it
+    Handled,
+    /// answered `400 Bad Request`
+    BadRequest,
+    /// answered `404 Not Found`
+    NotFound,
+    /// answered `414 URI Too Long`
+    UriTooLong,
+    /// answered `408 Request Timeout`: the headers did not arrive in time
+    TimedOut,
+    /// the client went away before the headers were complete
+    Disconnected,
+}
+
+impl ufmt::uDebug for Outcome {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        f.write_str(match self {
+            Outcome::Handled => "handled",
+            Outcome::BadRequest => "400 bad request",
+            Outcome::NotFound => "404 not found",
+            Outcome::UriTooLong => "414 URI too long",
+            Outcome::TimedOut => "408 request timeout",
+            Outcome::Disconnected => "client disconnected",
+        })
+    }
+}
+
+/// Dispatches requests to the first matching [`Route`].
+///
+/// ```
+/// const ROUTES: &[Route<Sensors>] = &[
+///     Route { method: Some(Method::Get), prefix: "/analog", handler: analog },
+///     Route { method: None, prefix: "/", handler: index },
+/// ];
+/// let router = Router::new(ROUTES);
+/// let mut buffer = [0u8; 256];
+/// loop {
+///     router.poll(&mut server, &mut buffer, &mut sensors);
+/// }
+/// ```
+pub struct Router<'r, C> {
+    routes: &'r [Route<C>],
+    timeout: u32,
+}
+
+impl<'r, C> Router<'r, C> {
+    pub fn new(routes: &'r [Route<C>]) -> Self {
+        Router {
+            routes,
+            timeout: DEFAULT_REQUEST_TIMEOUT_MS,
+        }
+    }
+
+    /// How long a client gets to send the request line and headers before it is answered with
+    /// `408 Request Timeout` and closed.  A client that connects and says nothing would otherwise
+    /// hold up every other connection.  The default is [`DEFAULT_REQUEST_TIMEOUT_MS`].
+    pub fn timeout(mut self, millis: u32) -> Self {
+        self.timeout = millis;
+        self
+    }
+
+    /// Serve one waiting connection from `server`, if there is one.  The request line and headers
+    /// have to fit in `buffer`.
+    pub fn poll(
+        &self,
+        server: &mut EthernetServer<'_>,
+        buffer: &mut [u8],
+        context: &mut C,
+    ) -> Option<Outcome> {
+        let mut client = server.available_safe()?;
+        Some(self.handle(&mut client, buffer, context))
+        // dropping the client closes the connection
+    }
+
+    /// Read one request from `client` and answer it.
+    pub fn handle(
+        &self,
+        client: &mut EthernetClient<'_>,
+        buffer: &mut [u8],
+        context: &mut C,
+    ) -> Outcome {
+        let request = match Request::read_within(client, buffer, self.timeout) {
+            Ok(request) => request,
+            Err(ParseError::BadRequest) => {
+                let _ = write_status(client, Status::BAD_REQUEST);
+                return Outcome::BadRequest;
+            }
+            Err(ParseError::UriTooLong) => {
+                let _ = write_status(client, Status::URI_TOO_LONG);
+                return Outcome::UriTooLong;
+            }
+            Err(ParseError::TimedOut) => {
+                let _ = write_status(client, Status::REQUEST_TIMEOUT);
+                return Outcome::TimedOut;
+            }
+            Err(ParseError::Disconnected) => return Outcome::Disconnected,
+        };
+        match self.routes.iter().find(|route| route.matches(&request)) {
+            Some(route) => {
+                (route.handler)(context, &request, client);
+                Outcome::Handled
+            }
+            None => {
+                let _ = write_status(client, Status::NOT_FOUND);
+                Outcome::NotFound
+            }
+        }
+    }
+}
+
+/// a complete response with no body, e.g. `write_status(client, Status::NOT_FOUND)`
+pub fn write_status(client: &mut EthernetClient<'_>, status: Status) -> Result<(), IoError> {
+    Response::start(client, status)?.finish()
+}
@@ -0,0 +1,74 @@
+//! blocking [`embedded_io`] traits for [`EthernetClient`].
+//!
+//! `read` spins until at least one byte arrives, and reports end-of-stream as `Ok(0)` once the
+//! peer has closed and the receive buffer is drained.  `write` spins until the W5x00 has room.
+//! Use [`EthernetClient::try_read`] and [`EthernetClient::try_write`] if you would rather see
+//! [`IoError::WouldBlock`].
+//!
+//! embedded-io is an edition 2021 crate and supports Rust 1.60 or newer.
+
+use crate::{EthernetClient, IoError};
+use embedded_io::{ErrorKind, ErrorType, Read, ReadReady, Write, WriteReady};
+
+impl embedded_io::Error for IoError {
+    fn kind(&self) -> ErrorKind {
+        match self {
+            IoError::WouldBlock => ErrorKind::Interrupted,
+            IoError::NotConnected => ErrorKind::NotConnected,
+            IoError::PeerClosed => ErrorKind::ConnectionAborted,
+            IoError::Socket => ErrorKind::Other,
+            IoError::TimedOut => ErrorKind::TimedOut,
+        }
+    }
+}
+
+impl ErrorType for EthernetClient<'_> {
+    type Error = IoError;
+}
+
+impl Read for EthernetClient<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, IoError> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            match self.try_read(buf) {
+                Err(IoError::WouldBlock) => continue,
+                Err(IoError::PeerClosed) => return Ok(0),
+                rval => return rval,
+            }
+        }
+    }
+}
+
+impl ReadReady for EthernetClient<'_> {
+    fn read_ready(&mut self) -> Result<bool, IoError> {
+        // end-of-stream counts as ready, because read() would not block
+        Ok(self.available() > 0 || !self.connected())
+    }
+}
+
+impl Write for EthernetClient<'_> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, IoError> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            match self.try_write(buf) {
+                Err(IoError::WouldBlock) => continue,
+                rval => return rval,
+            }
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), IoError> {
+        EthernetClient::flush(self);
+        Ok(())
+    }
+}
+
+impl WriteReady for EthernetClient<'_> {
+    fn write_ready(&mut self) -> Result<bool, IoError> {
+        Ok(self.available_for_write() > 0 || !self.connected())
+    }
+}
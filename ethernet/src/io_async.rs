@@ -0,0 +1,24 @@
+//! [`embedded_io_async`](embedded_io_async_crate) traits for [`EthernetClient`], on top of the
+//! [`asynch`](crate::asynch) futures.  The error types are shared with [`embedded_io`], which
+//! the `embedded-io-async` feature turns on too.  `async fn` in traits needs Rust 1.75 or newer.
+
+use crate::{EthernetClient, IoError};
+use embedded_io_async_crate::{Read, Write};
+
+impl Read for EthernetClient<'_> {
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, IoError> {
+        self.read_async(buf).await
+    }
+}
+
+impl Write for EthernetClient<'_> {
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, IoError> {
+        self.write_async(buf).await
+    }
+
+    async fn flush(&mut self) -> Result<(), IoError> {
+        // write() only returns once the chip has sent the data, so this never waits long
+        EthernetClient::flush(self);
+        Ok(())
+    }
+}
@@ -1,15 +1,51 @@
 #![no_std]
 
+pub mod asynch;
+mod board;
+mod buffered;
+mod clients;
+mod config;
+mod connect;
+mod dhcp;
+mod dns;
+pub mod error;
+pub mod http;
+#[cfg(feature = "embedded-io")]
+mod io;
+#[cfg(feature = "embedded-io-async")]
+mod io_async;
+mod link;
+#[cfg(feature = "embedded-nal")]
+pub mod nal;
 pub mod raw;
+mod socket;
+mod token;
+mod udp;
 
 use crate::EthernetInitializationMalfunction::{DhcpFailed, MissingHardware};
 use avr_hal_generic::port::mode::Output;
 use avr_hal_generic::port::Pin;
+pub use board::Board;
+pub use buffered::{BufferedClient, BufferedWriter, DEFAULT_READ_TIMEOUT_MS};
+pub use clients::ClientSet;
+use config::Retransmission;
+pub use config::{NetworkConfig, RetransmissionTimeout, DEFAULT_RETRANSMISSION_COUNT};
+pub use connect::{ConnectPoll, PendingConnect};
 use core::convert::TryInto;
-pub use raw::{EthernetClient, EthernetServer, EthernetUDP};
+use core::mem::ManuallyDrop;
+use core::ptr;
+pub use dhcp::{DhcpLease, MaintainOutcome};
+pub use dns::DEFAULT_DNS_TIMEOUT_MS;
+pub use error::{
+    ConnectError, DnsError, Error, IoError, LineError, NoFreeSocket, UdpError, WriteError,
+};
+pub use link::{LinkEvent, LinkSupervisor};
 use rust_arduino_helpers::NumberedPin;
 pub use rust_arduino_runtime::client::Client;
 pub use rust_arduino_runtime::ip_address::IPAddress;
+pub use socket::{socket_capacity, sockets_in_use, SocketHandle};
+pub use token::EthernetToken;
+pub use udp::{EthernetUDP, MulticastUdp};
 use ufmt::{uWrite, Formatter};
 
 pub enum LinkStatus {
@@ -57,7 +93,7 @@ impl From<u16> for HardwareStatus {
 pub enum EthernetInitializationMalfunction<P: NumberedPin> {
     DhcpFailed(EthernetBuilder<P>),
     LinkOff(EthernetBuilder<P>),
-    MissingHardware(Pin<Output, P>),
+    MissingHardware(Pin<Output, P>, EthernetToken),
 }
 
 impl<P: NumberedPin> core::fmt::Debug for EthernetInitializationMalfunction<P> {
@@ -67,7 +103,7 @@ impl<P: NumberedPin> core::fmt::Debug for EthernetInitializationMalfunction<P> {
             EthernetInitializationMalfunction::LinkOff(_) => {
                 f.write_str("Link Off (cable unplugged?)")
             }
-            MissingHardware(_) => f.write_str("Missing ethernet hardware"),
+            MissingHardware(..) => f.write_str("Missing ethernet hardware"),
         }
     }
 }
@@ -82,7 +118,7 @@ impl<P: NumberedPin> ufmt::uDebug for EthernetInitializationMalfunction<P> {
             EthernetInitializationMalfunction::LinkOff(_) => {
                 f.write_str("Link Off (cable unplugged?)")
             }
-            MissingHardware(_) => f.write_str("Missing ethernet hardware"),
+            MissingHardware(..) => f.write_str("Missing ethernet hardware"),
         }
     }
 }
@@ -91,6 +127,8 @@ impl<P: NumberedPin> ufmt::uDebug for EthernetInitializationMalfunction<P> {
 
 pub struct EthernetBuilder<P: NumberedPin> {
     pin: Pin<Output, P>,
+    token: EthernetToken,
+    retransmission: Retransmission,
 }
 
 impl<P: NumberedPin> EthernetBuilder<P> {
@@ -110,14 +148,21 @@ impl<P: NumberedPin> EthernetBuilder<P> {
             match self.link_status() {
                 LinkStatus::LinkOn => Err(DhcpFailed(self)),
                 LinkStatus::LinkOff => Err(EthernetInitializationMalfunction::LinkOff(self)),
-                LinkStatus::Unknown | LinkStatus::Madness(_) => Err(MissingHardware(self.pin)),
+                LinkStatus::Unknown | LinkStatus::Madness(_) => {
+                    Err(MissingHardware(self.pin, self.token))
+                }
             }
         }
     }
 
     fn with_pin(self) -> EthernetWrapper<P> {
-        unsafe { raw::EthernetClass_init(P::pin_number()) }
-        EthernetWrapper { pin: self.pin }
+        self.retransmission.apply();
+        socket::probe_capacity();
+        EthernetWrapper {
+            pin: self.pin,
+            token: self.token,
+            retransmission: self.retransmission,
+        }
     }
 
     pub fn static_ip(
@@ -158,11 +203,16 @@ impl<P: NumberedPin> EthernetBuilder<P> {
     pub fn error_if_no_hardware(self) -> Result<Self, EthernetInitializationMalfunction<P>> {
         match self.hardware_status() {
             HardwareStatus::NoHardware | HardwareStatus::Madness(_) => {
-                Err(EthernetInitializationMalfunction::MissingHardware(self.pin))
+                Err(MissingHardware(self.pin, self.token))
             }
             _ => Ok(self),
         }
     }
+
+    /// give up on starting the stack, and get the pin and token back
+    pub fn release(self) -> (Pin<Output, P>, EthernetToken) {
+        (self.pin, self.token)
+    }
 }
 
 //
@@ -171,51 +221,75 @@ impl<P: NumberedPin> EthernetBuilder<P> {
 /// let dp = arduino_hal::Peripherals::take().unwrap();
 /// let pins = pins!(dp);
 /// let mut mac = [0xde, 0xad, 0xbe, 0xef, 1, 2];
-/// let ethernet = EthernetWrapper::builder(pins.d10.into_output())
+/// let ethernet = EthernetWrapper::builder(EthernetToken::take().unwrap(), pins.d10.into_output())
 ///         .static_ip(&mut mac, ip_address_4(192, 168, 8, 167)) ?;
 /// ```
 pub struct EthernetWrapper<P: NumberedPin> {
     pin: Pin<Output, P>,
+    token: EthernetToken,
+    retransmission: Retransmission,
 }
 
 impl<P: NumberedPin> EthernetWrapper<P> {
-    pub fn tcp_listen(&self, port: u16) -> EthernetServer {
+    pub fn tcp_listen(&self, port: u16) -> Result<EthernetServer<'_>, NoFreeSocket> {
+        let socket = SocketHandle::reserve()?;
         unsafe {
-            let mut rval = raw::fabricate_EthernetServer(port);
-            raw::virtual_EthernetServer_begin(&mut rval as *mut EthernetServer); // would you ever NOT want to call `.begin()`?
-            rval
+            let mut inner = raw::fabricate_EthernetServer(port);
+            raw::virtual_EthernetServer_begin(&mut inner as *mut raw::EthernetServer); // would you ever NOT want to call `.begin()`?
+            Ok(EthernetServer {
+                inner,
+                _socket: socket,
+            })
         }
     }
 }
 
 impl<P: NumberedPin> EthernetWrapper<P> {
-    /// which pin?
-    ///
-    /// 10: most shields
-    ///
-    /// 5: MKR Eth Shield
-    ///
-    /// 0: Teensy 2.0
-    ///
-    /// 20: Teensy++ 2.0
+    /// which pin?  10 on most shields, see [`Board`] for the others (and [`builder_for`](Self::builder_for)).
     ///
-    /// 15: ESP8266 with Adafruit FeatherWing
+    /// The library is pointed at `spi_cs_pin` right away, so the `begin` and status calls on the
+    /// builder talk to the right chip.
     ///
-    /// 33: ESP32 with Adafruit FeatherWing
-    pub fn builder(spi_cs_pin: Pin<Output, P>) -> EthernetBuilder<P> {
-        EthernetBuilder { pin: spi_cs_pin }
+    /// There can only be one Ethernet stack, so this consumes the [`EthernetToken`].
+    pub fn builder(token: EthernetToken, spi_cs_pin: Pin<Output, P>) -> EthernetBuilder<P> {
+        unsafe { raw::EthernetClass_init(P::pin_number()) }
+        EthernetBuilder {
+            pin: spi_cs_pin,
+            token,
+            retransmission: Retransmission::default(),
+        }
     }
 
     pub fn link_status(&self) -> LinkStatus {
         unsafe { raw::EthernetClass::linkStatus().into() }
     }
 
-    pub fn new_udp(&self, port: u16) -> EthernetUDP {
-        let mut rval = ::core::mem::MaybeUninit::uninit();
-        unsafe {
-            raw::EthernetUDP_begin(rval.as_mut_ptr() as *mut cty::c_void, port);
+    pub fn new_udp(&self, port: u16) -> Result<EthernetUDP<'_>, UdpError> {
+        let mut rval = EthernetUDP::new(SocketHandle::reserve()?);
+        let code = unsafe { raw::EthernetUDP_begin(rval.this(), port) };
+        if code == 1 {
+            Ok(rval)
+        } else {
+            Err(UdpError::OpenFailed)
+        }
+    }
 
-            rval.assume_init()
+    /// Join the multicast `group` and listen for its datagrams on `port`.
+    /// The group is left when the returned socket is dropped.
+    pub fn new_udp_multicast(
+        &self,
+        group: IPAddress,
+        port: u16,
+    ) -> Result<MulticastUdp<'_>, UdpError> {
+        if !is_multicast(&group) {
+            return Err(UdpError::NotMulticast);
+        }
+        let mut rval = EthernetUDP::new(SocketHandle::reserve()?);
+        let code = unsafe { raw::EthernetUDP_beginMulticast(rval.this(), group, port) };
+        if code == 1 {
+            Ok(MulticastUdp { inner: rval })
+        } else {
+            Err(UdpError::OpenFailed)
         }
     }
 
@@ -223,108 +297,320 @@ impl<P: NumberedPin> EthernetWrapper<P> {
         unsafe { raw::EthernetClass_localIP() }
     }
 
-    pub fn dns_server_ip(&self) -> &'static IPAddress {
+    pub fn dns_server_ip(&self) -> &IPAddress {
         unsafe { &raw::EthernetClass__dnsServerAddress } // stupid inline method
     }
 
-    pub fn reclaim_pin(self) -> Pin<Output, P> {
-        self.pin
+    /// Shut down the wrapper and get the CS pin and the [`EthernetToken`] back.  Every client,
+    /// server and UDP socket borrows the wrapper, so they all have to be dropped first.
+    pub fn reclaim_pin(self) -> (Pin<Output, P>, EthernetToken) {
+        (self.pin, self.token)
     }
 
-    pub fn tcp_connect_hostname(
+    /// Open a TCP connection to `ip:port`.  This blocks until the handshake completes or the
+    /// library gives up, which is after the client's 1 second connection timeout, or sooner if
+    /// the chip runs out of retransmissions (an unanswered ARP gives up after
+    /// `timeout * (count + 1)`, see [`RetransmissionTimeout`]).  To keep the main loop running
+    /// meanwhile, use [`tcp_connect_nonblocking`](Self::tcp_connect_nonblocking).
+    pub fn tcp_connect(
         &self,
-        host_name: &cstr_core::CStr,
+        ip: IPAddress,
         port: u16,
-    ) -> Result<EthernetClient, i16> {
-        let mut rval = EthernetClient::new();
-
-        let return_code = unsafe {
-            raw::virtual_EthernetClient_connect_hostname(
-                &mut rval as *mut EthernetClient,
-                host_name.as_ptr() as *const i8,
-                port,
-            )
-        };
+    ) -> Result<EthernetClient<'_>, ConnectError> {
+        match ip_address_octets(&ip) {
+            [0, 0, 0, 0] | [255, 255, 255, 255] => return Err(ConnectError::InvalidAddress),
+            _ => {}
+        }
+        let mut rval = EthernetClient::new(SocketHandle::reserve()?);
+
+        let return_code = unsafe { raw::virtual_EthernetClient_connect_ip(rval.this(), ip, port) };
         if return_code != 0 {
             Ok(rval)
         } else {
-            Err(return_code)
+            Err(ConnectError::Failed)
         }
     }
 
-    pub fn make_client(&self) -> EthernetClient {
-        EthernetClient::new()
+    /// [`resolve`](Self::resolve) `host_name`, then [`tcp_connect`](Self::tcp_connect) to it.
+    ///
+    /// Worst case this blocks for 3 DNS attempts, each of which can spend the ARP give-up time
+    /// sending the query and [`DEFAULT_DNS_TIMEOUT_MS`] waiting for the answer, plus the
+    /// [`tcp_connect`](Self::tcp_connect) time.  With the chip defaults that is about
+    /// 3 × (1.8s + 5s) + 1s ≈ 21.4 seconds.
+    pub fn tcp_connect_hostname(
+        &self,
+        host_name: &cstr_core::CStr,
+        port: u16,
+    ) -> Result<EthernetClient<'_>, ConnectError> {
+        let ip = self.resolve(host_name)?;
+        self.tcp_connect(ip, port)
     }
-}
 
-impl EthernetUDP {
-    pub fn send_to(
-        &mut self,
-        destination_ip: IPAddress,
-        destination_port: u16,
-        payload: &mut [u8],
-    ) -> raw::size_t {
-        use cty::*;
-        unsafe {
-            let this = self as *mut Self as *mut c_void;
-            let n1 = raw::EthernetUDP_beginPacket(this, destination_ip, destination_port);
-            let packet_len: u16 = payload.len().try_into().unwrap();
-            let n2 = raw::EthernetUDP_write1(this, payload.as_mut_ptr(), packet_len);
-            let n3 = raw::EthernetUDP_endPacket(this);
+    /// an unconnected client, for C++ code like PubSubClient which connects it by itself
+    pub fn make_client(&self) -> Result<EthernetClient<'_>, NoFreeSocket> {
+        Ok(EthernetClient::new(SocketHandle::reserve()?))
+    }
 
-            n1 as c_uint + n2 + n3 as c_uint
-        }
+    /// how many more clients, servers and UDP sockets can be opened
+    pub fn sockets_free(&self) -> u8 {
+        socket_capacity().saturating_sub(sockets_in_use())
     }
 }
 
+/// A TCP server socket listening on a port.  It holds one of the hardware sockets, and borrows
+/// the [`EthernetWrapper`] for `'a`.  Dropping it stops listening; connections it already
+/// handed out stay open.
+///
 /// To create one of these, use [`EthernetWrapper::tcp_listen`]
-impl EthernetServer {
-    pub fn available_safe(&mut self) -> Option<EthernetClient> {
-        let rval = unsafe { self.available() };
-        if rval.valid() {
-            Some(rval)
-        } else {
-            None
+pub struct EthernetServer<'a> {
+    inner: raw::EthernetServer,
+    _socket: SocketHandle<'a>,
+}
+
+impl<'a> EthernetServer<'a> {
+    /// A connection with bytes waiting to be read.  The server keeps the connection in its set
+    /// (see [`write_all_clients`](Self::write_all_clients)), so the same one can come back
+    /// again; the client borrows the server so that there is only ever one of it.  Dropping it
+    /// closes the connection.  To keep a connection around, use [`accept`](Self::accept).
+    pub fn available_safe(&mut self) -> Option<EthernetClient<'_>> {
+        let rval = unsafe { self.inner.available() };
+        EthernetClient::adopt(rval)
+    }
+
+    /// The next new connection, whether or not it has sent anything.  Each connection is
+    /// returned only once, and leaves the server's set: it is yours until you drop it, and
+    /// [`write_all_clients`](Self::write_all_clients) no longer reaches it.  Keep several of
+    /// them in a [`ClientSet`].
+    pub fn accept(&mut self) -> Option<EthernetClient<'a>> {
+        let rval = unsafe { self.inner.accept() };
+        EthernetClient::adopt(rval)
+    }
+
+    /// Send `data` to every connection the server still holds, i.e. every connected peer that
+    /// was not taken with [`accept`](Self::accept).  This also closes the connections whose peers
+    /// have hung up.  Returns how many connections are still open afterwards.
+    pub fn write_all_clients(&mut self, data: &[u8]) -> u8 {
+        if !data.is_empty() {
+            unsafe {
+                raw::virtual_EthernetServer_write(
+                    &mut self.inner as *mut raw::EthernetServer,
+                    data.as_ptr(),
+                    data.len().try_into().unwrap(),
+                );
+            }
         }
+        self.client_count()
     }
+
+    /// how many connections the server holds, see [`write_all_clients`](Self::write_all_clients)
+    pub fn client_count(&self) -> u8 {
+        unsafe { raw::EthernetServer_client_count(self.port()) }
+    }
+
+    pub fn port(&self) -> u16 {
+        self.inner._port
+    }
+
+    /// Give up ownership without closing the listening socket, e.g. to hand it to C++ code.
+    /// The hardware socket stays counted as in use.
+    pub fn into_raw(self) -> raw::EthernetServer {
+        let this = ManuallyDrop::new(self);
+        // the SocketHandle is never dropped, so the socket stays reserved
+        unsafe { ptr::read(&this.inner) }
+    }
+}
+
+impl Drop for EthernetServer<'_> {
+    fn drop(&mut self) {
+        unsafe { raw::EthernetServer_close_listeners(self.port()) }
+    }
+}
+
+/// A TCP connection.  It holds one of the hardware sockets, and borrows the [`EthernetWrapper`]
+/// for `'a`.
+///
+/// Dropping it flushes and closes the connection.  The close waits for the peer to acknowledge
+/// for at most the [connection timeout](Self::set_connection_timeout); the flush is bounded by
+/// the chip giving up on retransmissions (see [`RetransmissionTimeout`]).
+///
+/// To create one of these, use [`EthernetWrapper::tcp_connect`], [`EthernetServer::accept`] or
+/// [`EthernetServer::available_safe`]
+pub struct EthernetClient<'a> {
+    inner: raw::EthernetClient,
+    _socket: SocketHandle<'a>,
+    write_timeout: u32,
 }
 
-impl EthernetClient {
-    fn new() -> Self {
-        unsafe { raw::fabricate_EthernetClient() }
+/// how long [`EthernetClient::write_all`] waits for room in the transmit buffer, unless
+/// [changed](EthernetClient::set_write_timeout)
+pub const DEFAULT_WRITE_TIMEOUT_MS: u32 = 5000;
+
+impl<'a> EthernetClient<'a> {
+    fn new(socket: SocketHandle<'a>) -> Self {
+        EthernetClient {
+            inner: unsafe { raw::fabricate_EthernetClient() },
+            _socket: socket,
+            write_timeout: DEFAULT_WRITE_TIMEOUT_MS,
+        }
+    }
+
+    /// wrap a socket connected by hand, see [`PendingConnect`]
+    pub(crate) fn from_socket(socket: u8, handle: SocketHandle<'a>) -> Self {
+        EthernetClient {
+            inner: unsafe { raw::fabricate_EthernetClient_socket(socket) },
+            _socket: handle,
+            write_timeout: DEFAULT_WRITE_TIMEOUT_MS,
+        }
+    }
+
+    /// Wrap a connection the library handed us, if it really is one.  If the socket budget is
+    /// already spent the connection is closed instead, so the count never exceeds the chip.
+    pub(crate) fn adopt(mut inner: raw::EthernetClient) -> Option<Self> {
+        if !unsafe { raw::EthernetClient_valid(&inner as *const raw::EthernetClient) } {
+            return None;
+        }
+        match SocketHandle::adopt() {
+            Ok(socket) => Some(EthernetClient {
+                inner,
+                _socket: socket,
+                write_timeout: DEFAULT_WRITE_TIMEOUT_MS,
+            }),
+            Err(NoFreeSocket) => {
+                unsafe { raw::virtual_EthernetClient_stop(&mut inner as *mut raw::EthernetClient) }
+                None
+            }
+        }
+    }
+
+    fn this(&mut self) -> *mut raw::EthernetClient {
+        &mut self.inner as *mut raw::EthernetClient
+    }
+
+    fn this_const(&self) -> *const raw::EthernetClient {
+        &self.inner as *const raw::EthernetClient
     }
 
     pub fn available_for_write(&mut self) -> i16 {
-        unsafe { raw::virtual_EthernetClient_availableForWrite(self as *mut EthernetClient) }
+        unsafe { raw::virtual_EthernetClient_availableForWrite(self.this()) }
     }
 
     pub fn connected(&mut self) -> bool {
-        unsafe { raw::virtual_EthernetClient_connected(self as *mut EthernetClient) }
+        unsafe { raw::virtual_EthernetClient_connected(self.this()) }
     }
 
     pub fn available(&mut self) -> i16 {
-        unsafe { raw::virtual_EthernetClient_available(self as *mut EthernetClient) }
+        unsafe { raw::virtual_EthernetClient_available(self.this()) }
     }
 
-    pub fn write(&mut self, buffer: &[u8]) -> Result<(), SocketError> {
+    /// Send as much of `buffer` as the transmit buffer holds (2KB on a W5100), waiting for room
+    /// and for the chip to send it, and return how many bytes that was.  Anything past that is
+    /// not sent; use [`write_all`](Self::write_all) to send all of it.
+    ///
+    /// If the peer stops acknowledging, this blocks until the chip gives up on retransmitting,
+    /// [`RetransmissionTimeout::give_up_after`] (31.8 seconds with the chip defaults), and then
+    /// fails with [`IoError::Socket`].  Use [`try_write`](Self::try_write) to not wait at all.
+    pub fn write(&mut self, buffer: &[u8]) -> Result<usize, IoError> {
+        if buffer.is_empty() {
+            return Ok(0);
+        }
         let n = unsafe {
             raw::virtual_EthernetClient_write(
-                self as *mut EthernetClient,
+                self.this(),
                 buffer.as_ptr(),
                 buffer.len().try_into().unwrap(),
             )
         };
         if n == 0 {
-            // what is the error signaling method?  The base method returns a size_t which is unsigned
-            Err(SocketError::new("failed to write to socket"))
+            // The base method returns a size_t, so 0 is the only failure signal we get
+            Err(IoError::Socket)
+        } else {
+            Ok(n as usize)
+        }
+    }
+
+    /// Send all of `buffer`, a transmit buffer's worth at a time.  If the chip has no room for
+    /// longer than the [write timeout](Self::set_write_timeout) this gives up with
+    /// [`IoError::TimedOut`]; the error says how much of `buffer` was sent before that.
+    pub fn write_all(&mut self, buffer: &[u8]) -> Result<(), WriteError> {
+        let mut delivered = 0;
+        let mut last_progress = millis();
+        while delivered < buffer.len() {
+            let error = match self.try_write(&buffer[delivered..]) {
+                Ok(n) => {
+                    delivered += n;
+                    last_progress = millis();
+                    continue;
+                }
+                Err(IoError::WouldBlock) => {
+                    if millis().wrapping_sub(last_progress) < self.write_timeout {
+                        continue;
+                    }
+                    IoError::TimedOut
+                }
+                Err(error) => error,
+            };
+            return Err(WriteError { error, delivered });
+        }
+        Ok(())
+    }
+
+    /// How long [`write_all`](Self::write_all) waits for the peer to make room in the transmit
+    /// buffer.  The default is [`DEFAULT_WRITE_TIMEOUT_MS`].
+    pub fn set_write_timeout(&mut self, millis: u32) {
+        self.write_timeout = millis;
+    }
+
+    /// why there is nothing to read (or no room to write) right now
+    fn stalled(&mut self) -> IoError {
+        if self.connected() {
+            IoError::WouldBlock
+        } else if self.valid() {
+            IoError::PeerClosed
+        } else {
+            IoError::NotConnected
+        }
+    }
+
+    /// Read whatever has already arrived, without waiting.
+    ///
+    /// Returns [`IoError::WouldBlock`] if the connection is open but nothing is buffered, and
+    /// [`IoError::PeerClosed`] once the peer has closed and every byte has been read.
+    pub fn try_read(&mut self, dest: &mut [u8]) -> Result<usize, IoError> {
+        if self.available() > 0 {
+            match self.read_multi(dest) {
+                Ok(slice) => Ok(slice.len()),
+                Err(_) => Err(IoError::Socket),
+            }
+        } else {
+            Err(self.stalled())
+        }
+    }
+
+    /// Write as much of `buffer` as fits in the W5x00 transmit buffer right now, without waiting.
+    ///
+    /// Returns the number of bytes accepted, or [`IoError::WouldBlock`] if there was no room at all.
+    pub fn try_write(&mut self, buffer: &[u8]) -> Result<usize, IoError> {
+        if !self.connected() {
+            return Err(self.stalled());
+        }
+        let room = self.available_for_write();
+        if room <= 0 {
+            return Err(IoError::WouldBlock);
+        }
+        let len = buffer.len().min(room as usize);
+        let n = unsafe {
+            raw::virtual_EthernetClient_write(self.this(), buffer.as_ptr(), len.try_into().unwrap())
+        };
+        if n == 0 {
+            Err(IoError::Socket)
         } else {
-            Ok(())
+            Ok(n as usize)
         }
     }
 
     pub fn read(&mut self) -> Option<u8> {
         unsafe {
-            let rval = raw::virtual_EthernetClient_read(self as *mut EthernetClient);
+            let rval = raw::virtual_EthernetClient_read(self.this());
             if rval & 0xff == rval {
                 Some(rval as u8)
             } else {
@@ -333,66 +619,114 @@ impl EthernetClient {
         }
     }
 
-    pub fn read_multi<'a>(&mut self, dest: &'a mut [u8]) -> Result<&'a [u8], SocketError> {
+    /// Read up to `dest.len()` bytes that have already arrived.  When there are none you get
+    /// [`IoError::WouldBlock`], [`IoError::PeerClosed`] or [`IoError::NotConnected`].
+    pub fn read_multi<'b>(&mut self, dest: &'b mut [u8]) -> Result<&'b [u8], IoError> {
         let buf = dest.as_mut_ptr();
         let size = dest.len().try_into().unwrap();
-        let code = unsafe {
-            raw::virtual_EthernetClient_readMulti(self as *mut EthernetClient, buf, size)
-        };
+        let code = unsafe { raw::virtual_EthernetClient_readMulti(self.this(), buf, size) };
         if code > 0 {
             Ok(&dest[..(code as usize)])
         } else {
-            Err(SocketError::new("read returns nothing"))
+            Err(self.stalled())
         }
     }
 
     pub fn println(&mut self, msg: &[u8]) -> u16 {
-        unsafe { raw::virtual_EthernetClient_println(self as *mut EthernetClient, msg.as_ptr()) }
+        unsafe { raw::virtual_EthernetClient_println(self.this(), msg.as_ptr()) }
     }
 
     pub fn flush(&mut self) {
-        unsafe { raw::virtual_EthernetClient_flush(self as *mut EthernetClient) }
+        unsafe { raw::virtual_EthernetClient_flush(self.this()) }
     }
 
     pub fn stop(&mut self) {
-        unsafe { raw::virtual_EthernetClient_stop(self as *mut EthernetClient) }
+        unsafe { raw::virtual_EthernetClient_stop(self.this()) }
+    }
+
+    /// How long [`stop`](Self::stop) (and so dropping) waits for the peer to close, and how long
+    /// a connect waits for the handshake.  The library default is 1000ms.
+    pub fn set_connection_timeout(&mut self, millis: u16) {
+        self.inner._timeout = millis // stupid inline method
+    }
+
+    /// Give up ownership without closing the connection, e.g. to hand it to C++ code.
+    /// The hardware socket stays counted as in use.
+    pub fn into_raw(self) -> raw::EthernetClient {
+        let this = ManuallyDrop::new(self);
+        // the SocketHandle is never dropped, so the socket stays reserved
+        unsafe { ptr::read(&this.inner) }
     }
 
     pub fn valid(&self) -> bool {
-        unsafe { raw::EthernetClient_valid(self as *const EthernetClient) }
+        unsafe { raw::EthernetClient_valid(self.this_const()) }
     }
 
     pub fn remote_ip(&self) -> IPAddress {
-        unsafe { raw::virtual_EthernetClient_remoteIP(self as *const EthernetClient) }
+        unsafe { raw::virtual_EthernetClient_remoteIP(self.this_const()) }
+    }
+
+    pub fn remote_port(&self) -> u16 {
+        unsafe { raw::virtual_EthernetClient_remotePort(self.this_const()) }
+    }
+
+    /// index of the W5x00 hardware socket this client is using, or `None` if it does not have one
+    pub fn socket_number(&self) -> Option<u8> {
+        let s = unsafe { raw::EthernetClient_getSocketNumber(self.this_const()) };
+        if (s as u32) < raw::MAX_SOCK_NUM {
+            Some(s)
+        } else {
+            None
+        }
     }
 
     pub fn as_client_pointer(&self) -> *const Client {
         unsafe {
             // too lazy to create a second method for const
-            raw::cast_to_Client(self as *const EthernetClient as *mut EthernetClient)
-                as *const Client
+            raw::cast_to_Client(self.this_const() as *mut raw::EthernetClient) as *const Client
         }
     }
 
     pub fn as_client_mut_pointer(&mut self) -> *mut Client {
-        unsafe { raw::cast_to_Client(self as *mut EthernetClient) }
+        unsafe { raw::cast_to_Client(self.this()) }
     }
 }
 
-pub struct SocketError {
-    pub msg: &'static str,
-}
-impl SocketError {
-    pub fn new(msg: &'static str) -> SocketError {
-        SocketError { msg }
+impl Drop for EthernetClient<'_> {
+    fn drop(&mut self) {
+        self.flush();
+        self.stop();
     }
 }
 
-impl uWrite for EthernetClient {
-    type Error = SocketError;
+/// Every `write_str` is sent on its own with [`write_all`](EthernetClient::write_all); to batch
+/// the fragments of a `uwrite!`, go through a [`BufferedWriter`].
+impl uWrite for EthernetClient<'_> {
+    type Error = WriteError;
 
     fn write_str(&mut self, s: &str) -> Result<(), Self::Error> {
-        let buffer = s.as_bytes();
-        self.write(buffer)
+        self.write_all(s.as_bytes())
     }
 }
+
+extern "C" {
+    #[link_name = "millis"]
+    fn arduino_millis() -> rust_arduino_runtime::workaround_cty::c_ulong;
+}
+
+/// the Arduino core's millisecond clock, which wraps around every 49.7 days
+pub(crate) fn millis() -> u32 {
+    unsafe { arduino_millis() as u32 }
+}
+
+/// the four octets of `ip`, most significant first
+pub fn ip_address_octets(ip: &IPAddress) -> [u8; 4] {
+    let mut rval = [0u8; 4];
+    unsafe { raw::IPAddress_octets(ip as *const IPAddress, rval.as_mut_ptr()) };
+    rval
+}
+
+/// is `ip` in 224.0.0.0/4 ?
+pub fn is_multicast(ip: &IPAddress) -> bool {
+    ip_address_octets(ip)[0] & 0xf0 == 0xe0
+}
@@ -0,0 +1,265 @@
+use crate::{ip_address_octets, raw, EthernetWrapper, LinkStatus, MaintainOutcome, NetworkConfig};
+use rust_arduino_helpers::NumberedPin;
+use rust_arduino_runtime::ip_address::{ip_address_4, IPAddress};
+
+/// What [`LinkSupervisor::check`] and [`LinkSupervisor::reconfigure`] noticed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkEvent {
+    /// the cable was pulled (or the switch went away)
+    LinkDown,
+    /// the link is back, but the address has to be renewed or reapplied: drop every socket and
+    /// call [`reconfigure`](LinkSupervisor::reconfigure)
+    NeedsReconfigure,
+    /// the link is back and the address has been renewed or reapplied (or was never lost)
+    LinkUp,
+    /// DHCP did not answer.  It is retried after the
+    /// [retry interval](LinkSupervisor::retry_interval), which doubles after every failure.
+    DhcpFailed,
+}
+
+impl ufmt::uDebug for LinkEvent {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        f.write_str(match self {
+            LinkEvent::LinkDown => "link down",
+            LinkEvent::NeedsReconfigure => "link up, address lost",
+            LinkEvent::LinkUp => "link up",
+            LinkEvent::DhcpFailed => "DHCP failed",
+        })
+    }
+}
+
+/// the longest wait between DHCP attempts, however often they fail
+const MAX_RETRY_INTERVAL_MS: u32 = 300_000;
+
+/// how to get an address back once the link returns
+enum Reconfigure {
+    Dhcp {
+        mac: [u8; 6],
+        timeout: u32,
+        response_timeout: u32,
+    },
+    /// octets, because `IPAddress` can not be copied
+    Static {
+        ip: [u8; 4],
+        dns: [u8; 4],
+        gateway: [u8; 4],
+        subnet: [u8; 4],
+    },
+}
+
+/// Watches [`EthernetWrapper::link_status`] across calls to [`check`](Self::check) and gets the
+/// address back after the cable has been unplugged and plugged in again.
+///
+/// Checking only needs a shared borrow, so sockets can stay open.  Re-running DHCP resets the
+/// chip and rewriting a static address strands open connections, so that is a separate
+/// [`reconfigure`](Self::reconfigure) step, taking the wrapper by `&mut`, which is only due once
+/// [`check`](Self::check) has reported [`LinkEvent::NeedsReconfigure`].
+///
+/// The W5100 can not sense the link at all (it always reports [`LinkStatus::Unknown`]), so there
+/// the supervisor never sees a transition.  It calls [`EthernetWrapper::maintain`] instead, which
+/// keeps a DHCP lease alive (or gets a new one) without resetting the chip.
+///
+/// ```
+/// let mut supervisor = LinkSupervisor::dhcp(mac, 60_000, 4_000);
+/// loop {
+///     if let Some(event) = supervisor.check(&ethernet) {
+///         let _ = uwriteln!(&mut serial, "{:?}", event);
+///     }
+///     if supervisor.needs_reconfigure() {
+///         // every socket has been dropped by now
+///         if let Some(event) = supervisor.reconfigure(&mut ethernet) {
+///             let _ = uwriteln!(&mut serial, "{:?}", event);
+///         }
+///     }
+///     // ... open sockets, use them, drop them
+/// }
+/// ```
+pub struct LinkSupervisor {
+    reconfigure: Reconfigure,
+    link_up: Option<bool>,
+    retry_interval: u32,
+    /// the link came back and the address has not been restored yet
+    pending: bool,
+    /// DHCP failed, wait before the next attempt
+    retry: Option<Retry>,
+}
+
+/// when to try DHCP again
+struct Retry {
+    /// `millis()` at the last attempt
+    last_attempt: u32,
+    wait: u32,
+}
+
+impl LinkSupervisor {
+    /// re-run DHCP with the same arguments as [`EthernetBuilder::dhcp_lease`](crate::EthernetBuilder::dhcp_lease)
+    pub fn dhcp(mac: [u8; 6], timeout: u32, response_timeout: u32) -> Self {
+        Self::new(Reconfigure::Dhcp {
+            mac,
+            timeout,
+            response_timeout,
+        })
+    }
+
+    /// write `config` back into the chip
+    pub fn static_config(config: &NetworkConfig) -> Self {
+        Self::new(Reconfigure::Static {
+            ip: ip_address_octets(&config.ip),
+            dns: ip_address_octets(&config.dns),
+            gateway: ip_address_octets(&config.gateway),
+            subnet: ip_address_octets(&config.subnet),
+        })
+    }
+
+    fn new(reconfigure: Reconfigure) -> Self {
+        LinkSupervisor {
+            reconfigure,
+            link_up: None,
+            retry_interval: 10_000,
+            pending: false,
+            retry: None,
+        }
+    }
+
+    /// How long to wait before re-running DHCP after it failed, 10 seconds unless changed.  The
+    /// wait doubles after each failure, up to 5 minutes, since every attempt blocks the main loop
+    /// for the DHCP `timeout`.
+    pub fn retry_interval(mut self, millis: u32) -> Self {
+        self.retry_interval = millis;
+        self
+    }
+
+    /// Check the link, and report a change since the last call.  The first call only records
+    /// the current state.  This never resets the chip, so open sockets survive it.
+    ///
+    /// When the link comes back with a static configuration that the chip still holds, this
+    /// reports [`LinkEvent::LinkUp`] straight away; otherwise it reports
+    /// [`LinkEvent::NeedsReconfigure`].
+    pub fn check<P: NumberedPin>(&mut self, ethernet: &EthernetWrapper<P>) -> Option<LinkEvent> {
+        let now_up = match ethernet.link_status() {
+            LinkStatus::LinkOn => true,
+            LinkStatus::LinkOff => false,
+            LinkStatus::Unknown => return self.maintain(ethernet),
+            LinkStatus::Madness(_) => return None,
+        };
+        let was_up = self.link_up.replace(now_up);
+
+        match (was_up, now_up) {
+            (Some(true), false) => {
+                self.pending = false;
+                self.retry = None;
+                Some(LinkEvent::LinkDown)
+            }
+            (Some(false), true) if self.still_configured(ethernet) => Some(LinkEvent::LinkUp),
+            (Some(false), true) => {
+                self.pending = true;
+                Some(LinkEvent::NeedsReconfigure)
+            }
+            _ => None,
+        }
+    }
+
+    /// `true` once [`check`](Self::check) has reported [`LinkEvent::NeedsReconfigure`] and until
+    /// [`reconfigure`](Self::reconfigure) has restored the address.  Between retries of a failed
+    /// DHCP it is `false`, so the sockets only have to be dropped when an attempt is due.
+    pub fn needs_reconfigure(&self) -> bool {
+        self.pending
+            && match &self.retry {
+                Some(retry) => crate::millis().wrapping_sub(retry.last_attempt) >= retry.wait,
+                None => true,
+            }
+    }
+
+    /// Renew or reapply the address if [`needs_reconfigure`](Self::needs_reconfigure), and
+    /// report how that went.  Otherwise this returns `None` without touching the chip.
+    ///
+    /// Re-running DHCP resets the chip, so this takes the wrapper by `&mut`: every socket has to
+    /// be dropped before calling it.  DHCP blocks for up to `timeout` milliseconds.
+    pub fn reconfigure<P: NumberedPin>(
+        &mut self,
+        ethernet: &mut EthernetWrapper<P>,
+    ) -> Option<LinkEvent> {
+        if !self.needs_reconfigure() {
+            return None;
+        }
+        match &mut self.reconfigure {
+            Reconfigure::Dhcp {
+                mac,
+                timeout,
+                response_timeout,
+            } => {
+                let code = unsafe {
+                    raw::EthernetClass::begin(mac.as_mut_ptr(), *timeout, *response_timeout)
+                };
+                // begin() reset the chip
+                ethernet.retransmission.apply();
+                if code == 1 {
+                    self.pending = false;
+                    self.retry = None;
+                    return Some(LinkEvent::LinkUp);
+                }
+                let wait = match &self.retry {
+                    Some(retry) => retry.wait.saturating_mul(2).min(MAX_RETRY_INTERVAL_MS),
+                    None => self.retry_interval,
+                };
+                self.retry = Some(Retry {
+                    last_attempt: crate::millis(),
+                    wait,
+                });
+                Some(LinkEvent::DhcpFailed)
+            }
+            Reconfigure::Static {
+                ip,
+                dns,
+                gateway,
+                subnet,
+            } => {
+                ethernet.set_local_ip(to_ip_address(ip));
+                ethernet.set_dns_server_ip(to_ip_address(dns));
+                ethernet.set_gateway_ip(to_ip_address(gateway));
+                ethernet.set_subnet_mask(to_ip_address(subnet));
+                self.pending = false;
+                Some(LinkEvent::LinkUp)
+            }
+        }
+    }
+
+    /// whether the chip still holds the static configuration; a DHCP lease is always renewed,
+    /// since the cable may now lead to another network
+    fn still_configured<P: NumberedPin>(&self, ethernet: &EthernetWrapper<P>) -> bool {
+        match &self.reconfigure {
+            Reconfigure::Dhcp { .. } => false,
+            Reconfigure::Static {
+                ip,
+                dns: _,
+                gateway,
+                subnet,
+            } => {
+                ip_address_octets(&ethernet.local_ip()) == *ip
+                    && ip_address_octets(&ethernet.gateway_ip()) == *gateway
+                    && ip_address_octets(&ethernet.subnet_mask()) == *subnet
+            }
+        }
+    }
+
+    /// Without link sensing, keep the DHCP lease alive instead.  The library starts over with a
+    /// new DHCP request when rebinding fails, so only that is worth reporting.
+    fn maintain<P: NumberedPin>(&mut self, ethernet: &EthernetWrapper<P>) -> Option<LinkEvent> {
+        match self.reconfigure {
+            Reconfigure::Dhcp { .. } => match ethernet.maintain() {
+                MaintainOutcome::RebindFailed => Some(LinkEvent::DhcpFailed),
+                _ => None,
+            },
+            // the address registers survive a pulled cable
+            Reconfigure::Static { .. } => None,
+        }
+    }
+}
+
+fn to_ip_address(octets: &[u8; 4]) -> IPAddress {
+    let [a, b, c, d] = *octets;
+    ip_address_4(a, b, c, d)
+}
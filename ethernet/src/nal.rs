@@ -0,0 +1,302 @@
+//! [`embedded_nal`] stacks on top of [`EthernetWrapper`], so crates written against
+//! `TcpClientStack`, `TcpFullStack` and `UdpClientStack` can use the W5x00 shield.
+//!
+//! The stacks are implemented for `&EthernetWrapper`, because the sockets borrow the wrapper:
+//! `let mut stack = &ethernet;` and hand `&mut stack` to the crate.
+//!
+//! The Arduino `connect()` is blocking, so [`TcpClientStack::connect`] never returns
+//! `WouldBlock`; it either finishes the handshake or fails.  Everything else
+//! (`send`, `receive`, `accept`, UDP `receive`) polls the chip and returns
+//! [`nb::Error::WouldBlock`] instead of waiting.
+
+use crate::{
+    ip_address_octets, raw, ConnectError, EthernetClient, EthernetServer, EthernetUDP,
+    EthernetWrapper, IoError, NoFreeSocket, UdpError,
+};
+use embedded_nal::nb;
+use embedded_nal::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use embedded_nal::{TcpClientStack, TcpFullStack, UdpClientStack};
+use rust_arduino_helpers::NumberedPin;
+use rust_arduino_runtime::ip_address::{ip_address_4, IPAddress};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NalError {
+    /// the W5x00 only speaks IPv4
+    Ipv6Unsupported,
+    /// the socket is not in the right state for this operation (e.g. `send` before `connect`)
+    InvalidState,
+    /// the remote end closed the connection
+    PeerClosed,
+    /// the Arduino library could not open the connection
+    ConnectFailed,
+    /// every hardware socket is already in use
+    NoFreeSocket,
+    /// the chip refused the operation
+    WriteFailed,
+}
+
+impl ufmt::uDebug for NalError {
+    fn fmt<W>(&self, f: &mut ufmt::Formatter<'_, W>) -> Result<(), W::Error>
+    where
+        W: ufmt::uWrite + ?Sized,
+    {
+        f.write_str(match self {
+            NalError::Ipv6Unsupported => "IPv6 unsupported",
+            NalError::InvalidState => "socket in wrong state",
+            NalError::PeerClosed => "peer closed connection",
+            NalError::ConnectFailed => "connect failed",
+            NalError::NoFreeSocket => "no free hardware socket",
+            NalError::WriteFailed => "write failed",
+        })
+    }
+}
+
+impl From<NoFreeSocket> for NalError {
+    fn from(_: NoFreeSocket) -> Self {
+        NalError::NoFreeSocket
+    }
+}
+
+fn from_io(err: IoError) -> nb::Error<NalError> {
+    match err {
+        IoError::WouldBlock => nb::Error::WouldBlock,
+        IoError::NotConnected => nb::Error::Other(NalError::InvalidState),
+        IoError::PeerClosed => nb::Error::Other(NalError::PeerClosed),
+        IoError::Socket | IoError::TimedOut => nb::Error::Other(NalError::WriteFailed),
+    }
+}
+
+fn ipv4_of(remote: SocketAddr) -> Result<SocketAddrV4, NalError> {
+    match remote {
+        SocketAddr::V4(v4) => Ok(v4),
+        SocketAddr::V6(_) => Err(NalError::Ipv6Unsupported),
+    }
+}
+
+fn to_ip_address(ip: &Ipv4Addr) -> IPAddress {
+    let [a, b, c, d] = ip.octets();
+    ip_address_4(a, b, c, d)
+}
+
+fn to_socket_addr(ip: &IPAddress, port: u16) -> SocketAddr {
+    SocketAddr::V4(SocketAddrV4::new(
+        Ipv4Addr::from(ip_address_octets(ip)),
+        port,
+    ))
+}
+
+//
+
+enum TcpState<'a> {
+    Closed,
+    Bound(u16),
+    Listening(EthernetServer<'a>),
+    Connected(EthernetClient<'a>),
+}
+
+/// Handle for [`TcpClientStack::TcpSocket`].  Once connected (or accepted) it owns one of the
+/// W5x00 hardware sockets, see [`TcpSocket::socket_number`].
+pub struct TcpSocket<'a> {
+    state: TcpState<'a>,
+}
+
+impl<'a> TcpSocket<'a> {
+    /// which hardware socket carries this connection, if any
+    pub fn socket_number(&self) -> Option<u8> {
+        match &self.state {
+            TcpState::Connected(client) => client.socket_number(),
+            _ => None,
+        }
+    }
+
+    fn client(&mut self) -> Result<&mut EthernetClient<'a>, NalError> {
+        match &mut self.state {
+            TcpState::Connected(client) => Ok(client),
+            _ => Err(NalError::InvalidState),
+        }
+    }
+}
+
+impl<'a, P: NumberedPin> TcpClientStack for &'a EthernetWrapper<P> {
+    type TcpSocket = TcpSocket<'a>;
+    type Error = NalError;
+
+    fn socket(&mut self) -> Result<TcpSocket<'a>, NalError> {
+        Ok(TcpSocket {
+            state: TcpState::Closed,
+        })
+    }
+
+    fn connect(
+        &mut self,
+        socket: &mut TcpSocket<'a>,
+        remote: SocketAddr,
+    ) -> nb::Result<(), NalError> {
+        if let TcpState::Connected(_) | TcpState::Listening(_) = socket.state {
+            return Err(nb::Error::Other(NalError::InvalidState));
+        }
+        let remote = ipv4_of(remote)?;
+
+        match self.tcp_connect(to_ip_address(remote.ip()), remote.port()) {
+            Ok(client) => {
+                socket.state = TcpState::Connected(client);
+                Ok(())
+            }
+            Err(ConnectError::NoFreeSocket) => Err(nb::Error::Other(NalError::NoFreeSocket)),
+            Err(_) => Err(nb::Error::Other(NalError::ConnectFailed)),
+        }
+    }
+
+    fn is_connected(&mut self, socket: &TcpSocket<'a>) -> Result<bool, NalError> {
+        match &socket.state {
+            // connected() only reads the socket status, so the const pointer is safe to pass
+            TcpState::Connected(client) => Ok(unsafe {
+                raw::virtual_EthernetClient_connected(
+                    client.this_const() as *mut raw::EthernetClient
+                )
+            }),
+            _ => Ok(false),
+        }
+    }
+
+    fn send(&mut self, socket: &mut TcpSocket<'a>, buffer: &[u8]) -> nb::Result<usize, NalError> {
+        socket.client()?.try_write(buffer).map_err(from_io)
+    }
+
+    fn receive(
+        &mut self,
+        socket: &mut TcpSocket<'a>,
+        buffer: &mut [u8],
+    ) -> nb::Result<usize, NalError> {
+        socket.client()?.try_read(buffer).map_err(from_io)
+    }
+
+    fn close(&mut self, socket: TcpSocket<'a>) -> Result<(), NalError> {
+        // dropping the EthernetClient or EthernetServer closes it
+        drop(socket);
+        Ok(())
+    }
+}
+
+impl<'a, P: NumberedPin> TcpFullStack for &'a EthernetWrapper<P> {
+    fn bind(&mut self, socket: &mut TcpSocket<'a>, local_port: u16) -> Result<(), NalError> {
+        match socket.state {
+            TcpState::Closed | TcpState::Bound(_) => {
+                socket.state = TcpState::Bound(local_port);
+                Ok(())
+            }
+            _ => Err(NalError::InvalidState),
+        }
+    }
+
+    fn listen(&mut self, socket: &mut TcpSocket<'a>) -> Result<(), NalError> {
+        match socket.state {
+            TcpState::Bound(port) => {
+                socket.state = TcpState::Listening(self.tcp_listen(port)?);
+                Ok(())
+            }
+            _ => Err(NalError::InvalidState),
+        }
+    }
+
+    fn accept(
+        &mut self,
+        socket: &mut TcpSocket<'a>,
+    ) -> nb::Result<(TcpSocket<'a>, SocketAddr), NalError> {
+        let server = match &mut socket.state {
+            TcpState::Listening(server) => server,
+            _ => return Err(nb::Error::Other(NalError::InvalidState)),
+        };
+        if let Some(client) = server.accept() {
+            let remote = to_socket_addr(&client.remote_ip(), client.remote_port());
+            Ok((
+                TcpSocket {
+                    state: TcpState::Connected(client),
+                },
+                remote,
+            ))
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+}
+
+//
+
+/// Handle for [`UdpClientStack::UdpSocket`].  It grabs a hardware socket on an ephemeral local
+/// port when it is connected.
+pub struct UdpSocket<'a> {
+    udp: Option<EthernetUDP<'a>>,
+    remote: Option<SocketAddrV4>,
+}
+
+impl UdpSocket<'_> {
+    /// which hardware socket this handle is bound to, if any
+    pub fn socket_number(&self) -> Option<u8> {
+        match &self.udp {
+            Some(udp) => udp.socket_number(),
+            None => None,
+        }
+    }
+}
+
+impl<'a, P: NumberedPin> UdpClientStack for &'a EthernetWrapper<P> {
+    type UdpSocket = UdpSocket<'a>;
+    type Error = NalError;
+
+    fn socket(&mut self) -> Result<UdpSocket<'a>, NalError> {
+        Ok(UdpSocket {
+            udp: None,
+            remote: None,
+        })
+    }
+
+    fn connect(&mut self, socket: &mut UdpSocket<'a>, remote: SocketAddr) -> Result<(), NalError> {
+        let remote = ipv4_of(remote)?;
+        if socket.udp.is_none() {
+            // port 0 makes the library pick an ephemeral port
+            socket.udp = Some(self.new_udp(0).map_err(|e| match e {
+                UdpError::NoFreeSocket => NalError::NoFreeSocket,
+                _ => NalError::ConnectFailed,
+            })?);
+        }
+        socket.remote = Some(remote);
+        Ok(())
+    }
+
+    fn send(&mut self, socket: &mut UdpSocket<'a>, buffer: &[u8]) -> nb::Result<(), NalError> {
+        let (udp, remote) = match (&mut socket.udp, &socket.remote) {
+            (Some(udp), Some(remote)) => (udp, remote),
+            _ => return Err(nb::Error::Other(NalError::InvalidState)),
+        };
+        udp.send_to(to_ip_address(remote.ip()), remote.port(), buffer)
+            .map_err(|_| nb::Error::Other(NalError::WriteFailed))
+    }
+
+    fn receive(
+        &mut self,
+        socket: &mut UdpSocket<'a>,
+        buffer: &mut [u8],
+    ) -> nb::Result<(usize, SocketAddr), NalError> {
+        let udp = match &mut socket.udp {
+            Some(udp) => udp,
+            None => return Err(nb::Error::Other(NalError::InvalidState)),
+        };
+        match udp.recv_from(buffer) {
+            Ok((n, ip, port)) => Ok((n, to_socket_addr(&ip, port))),
+            // whatever did not fit in `buffer` is discarded, like a BSD socket would
+            Err(UdpError::Truncated { .. }) => Ok((
+                buffer.len(),
+                to_socket_addr(&udp.remote_ip(), udp.remote_port()),
+            )),
+            Err(UdpError::WouldBlock) => Err(nb::Error::WouldBlock),
+            Err(_) => Err(nb::Error::Other(NalError::InvalidState)),
+        }
+    }
+
+    fn close(&mut self, socket: UdpSocket<'a>) -> Result<(), NalError> {
+        // dropping the EthernetUDP closes it
+        drop(socket);
+        Ok(())
+    }
+}
@@ -0,0 +1,78 @@
+//! Bookkeeping for the W5x00's hardware sockets.  The W5100 has 4 and the W5200/W5500 have 8
+//! (further limited by the library's `MAX_SOCK_NUM`), and every [`EthernetClient`](crate::EthernetClient),
+//! [`EthernetServer`](crate::EthernetServer) and [`EthernetUDP`](crate::EthernetUDP) uses one.
+//! Running out used to look like a random connect failure; now it is [`NoFreeSocket`].
+//!
+//! DNS lookups and DHCP renewals borrow a UDP socket inside the library for the length of the
+//! call.  [`resolve`](crate::EthernetWrapper::resolve) reserves one around the lookup; a
+//! renewal in [`maintain`](crate::EthernetWrapper::maintain) simply fails while every socket is
+//! taken, and is tried again on a later call.
+
+use crate::{raw, HardwareStatus, NoFreeSocket};
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+/// how many [`SocketHandle`]s are alive.  The AVR has no compare-and-swap, so this is only
+/// updated with plain loads and stores; sockets are only created and dropped from the main loop,
+/// just like the rest of the Arduino library's global state.
+static SOCKETS_IN_USE: AtomicU8 = AtomicU8::new(0);
+
+/// [`socket_capacity`], read from the chip once when the [`EthernetWrapper`](crate::EthernetWrapper)
+/// is built.  0 until then.
+static CAPACITY: AtomicU8 = AtomicU8::new(0);
+
+/// how many hardware sockets the chip on the shield has, limited by `MAX_SOCK_NUM`
+pub fn socket_capacity() -> u8 {
+    CAPACITY.load(Ordering::Relaxed)
+}
+
+/// ask the chip which one it is, so [`socket_capacity`] does not have to on every reserve
+pub(crate) fn probe_capacity() {
+    let chip = match HardwareStatus::from(unsafe { raw::EthernetClass::hardwareStatus() }) {
+        HardwareStatus::W5100 => 4,
+        _ => 8,
+    };
+    CAPACITY.store(chip.min(raw::MAX_SOCK_NUM as u8), Ordering::Relaxed);
+}
+
+/// how many hardware sockets are currently held by a client, server or UDP socket
+pub fn sockets_in_use() -> u8 {
+    SOCKETS_IN_USE.load(Ordering::Relaxed)
+}
+
+/// One hardware socket's worth of the budget.  It goes back to the pool when dropped.
+///
+/// The handle borrows the [`EthernetWrapper`](crate::EthernetWrapper) it came from for `'a`, so
+/// every socket built on it has to be gone before
+/// [`reclaim_pin`](crate::EthernetWrapper::reclaim_pin) can hand the CS pin back.
+pub struct SocketHandle<'a> {
+    _wrapper: PhantomData<&'a ()>,
+}
+
+impl<'a> SocketHandle<'a> {
+    /// claim a socket before asking the library to open one
+    pub(crate) fn reserve() -> Result<SocketHandle<'a>, NoFreeSocket> {
+        let in_use = sockets_in_use();
+        if in_use >= socket_capacity() {
+            Err(NoFreeSocket)
+        } else {
+            SOCKETS_IN_USE.store(in_use + 1, Ordering::Relaxed);
+            Ok(SocketHandle {
+                _wrapper: PhantomData,
+            })
+        }
+    }
+
+    /// Account for a socket the library has already opened on its own (an accepted connection).
+    /// This fails when the budget is spent, e.g. because the server kept listening on a socket
+    /// that was never reserved.
+    pub(crate) fn adopt() -> Result<SocketHandle<'a>, NoFreeSocket> {
+        Self::reserve()
+    }
+}
+
+impl Drop for SocketHandle<'_> {
+    fn drop(&mut self) {
+        SOCKETS_IN_USE.store(sockets_in_use().saturating_sub(1), Ordering::Relaxed);
+    }
+}
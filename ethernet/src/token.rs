@@ -0,0 +1,31 @@
+//! The Arduino `EthernetClass` is one big pile of global state, so there may only be one
+//! [`EthernetWrapper`](crate::EthernetWrapper) at a time.  [`EthernetToken::take`] hands out
+//! the permission to build it exactly once, like `arduino_hal::Peripherals::take()`.
+
+use avr_hal_generic::avr_device::interrupt;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// has the token been taken?  There is no compare-and-swap on the AVR, so the load and store
+/// happen with interrupts off instead.
+static TAKEN: AtomicBool = AtomicBool::new(false);
+
+/// Permission to run the Ethernet stack.  [`EthernetWrapper::builder`](crate::EthernetWrapper::builder)
+/// consumes it, and you get it back from [`EthernetWrapper::reclaim_pin`](crate::EthernetWrapper::reclaim_pin)
+/// (or [`EthernetBuilder::release`](crate::EthernetBuilder::release)).
+pub struct EthernetToken {
+    _private: (),
+}
+
+impl EthernetToken {
+    /// `Some` the first time, `None` ever after
+    pub fn take() -> Option<EthernetToken> {
+        interrupt::free(|_| {
+            if TAKEN.load(Ordering::Relaxed) {
+                None
+            } else {
+                TAKEN.store(true, Ordering::Relaxed);
+                Some(EthernetToken { _private: () })
+            }
+        })
+    }
+}
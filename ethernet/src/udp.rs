@@ -0,0 +1,154 @@
+use crate::{raw, IPAddress, SocketHandle, UdpError};
+use core::convert::TryInto;
+use core::ops::{Deref, DerefMut};
+
+/// A UDP socket.  It holds one of the hardware sockets, and closes it when dropped.  It borrows
+/// the [`EthernetWrapper`](crate::EthernetWrapper) for `'a`.
+///
+/// To create one of these, use [`EthernetWrapper::new_udp`](crate::EthernetWrapper::new_udp)
+pub struct EthernetUDP<'a> {
+    inner: raw::EthernetUDP,
+    _socket: SocketHandle<'a>,
+}
+
+impl<'a> EthernetUDP<'a> {
+    pub(crate) fn new(socket: SocketHandle<'a>) -> Self {
+        EthernetUDP {
+            // the constructor has to run, or the vtable and socket index are garbage
+            inner: unsafe { raw::fabricate_EthernetUDP() },
+            _socket: socket,
+        }
+    }
+
+    /// Send `payload` as one datagram to `destination_ip:destination_port`
+    pub fn send_to(
+        &mut self,
+        destination_ip: IPAddress,
+        destination_port: u16,
+        payload: &[u8],
+    ) -> Result<(), UdpError> {
+        let this = self.this();
+        unsafe {
+            if raw::EthernetUDP_beginPacket(this, destination_ip, destination_port) == 0 {
+                return Err(UdpError::InvalidDestination);
+            }
+            let packet_len: u16 = payload.len().try_into().unwrap();
+            let written = raw::EthernetUDP_write1(this, payload.as_ptr(), packet_len);
+            if written != packet_len {
+                // still have to end the packet, or the next send_to will append to it
+                raw::EthernetUDP_endPacket(this);
+                return Err(UdpError::PayloadTooLong {
+                    written: written as usize,
+                });
+            }
+            if raw::EthernetUDP_endPacket(this) == 0 {
+                return Err(UdpError::SendFailed);
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn this(&mut self) -> *mut cty::c_void {
+        &mut self.inner as *mut raw::EthernetUDP as *mut cty::c_void
+    }
+
+    /// index of the W5x00 hardware socket, or `None` if `begin` failed
+    pub fn socket_number(&self) -> Option<u8> {
+        if (self.inner.sockindex as u32) < raw::MAX_SOCK_NUM {
+            Some(self.inner.sockindex)
+        } else {
+            None
+        }
+    }
+
+    /// Start processing the next incoming datagram, discarding whatever was left of the previous one.
+    ///
+    /// Returns the length of the datagram, or `None` if nothing is waiting.
+    /// After this [`remote_ip`](Self::remote_ip) and [`remote_port`](Self::remote_port) describe the sender.
+    pub fn parse_packet(&mut self) -> Option<usize> {
+        let len = unsafe { raw::EthernetUDP_parsePacket(self.this()) };
+        if len > 0 {
+            Some(len as usize)
+        } else {
+            None
+        }
+    }
+
+    /// number of bytes of the current datagram which have not been read yet
+    pub fn available(&mut self) -> usize {
+        let n = unsafe { raw::EthernetUDP_available(self.this()) };
+        n.max(0) as usize
+    }
+
+    /// Copy the next bytes of the current datagram into `dest`.
+    /// Returns how many bytes were copied (0 once the datagram is used up).
+    pub fn read(&mut self, dest: &mut [u8]) -> usize {
+        let n = unsafe {
+            raw::EthernetUDP_read1(
+                self.this(),
+                dest.as_mut_ptr(),
+                dest.len().try_into().unwrap(),
+            )
+        };
+        n.max(0) as usize
+    }
+
+    /// discard the rest of the current datagram
+    pub fn flush(&mut self) {
+        unsafe { raw::EthernetUDP_flush(self.this()) }
+    }
+
+    /// sender of the datagram most recently returned by [`parse_packet`](Self::parse_packet)
+    pub fn remote_ip(&mut self) -> IPAddress {
+        unsafe { raw::virtual_EthernetUDP_remoteIP(&mut self.inner) }
+    }
+
+    /// source port of the datagram most recently returned by [`parse_packet`](Self::parse_packet)
+    pub fn remote_port(&mut self) -> u16 {
+        unsafe { raw::virtual_EthernetUDP_remotePort(&mut self.inner) }
+    }
+
+    /// Receive one whole datagram into `buf`.
+    ///
+    /// Returns the length and the sender's address and port, or [`UdpError::WouldBlock`] if nothing
+    /// is waiting.  A datagram longer than `buf` is not silently cut short: you get
+    /// [`UdpError::Truncated`], `buf` holds the first `buf.len()` bytes, the rest is discarded and
+    /// [`remote_ip`](Self::remote_ip)/[`remote_port`](Self::remote_port) still name the sender.
+    pub fn recv_from(&mut self, buf: &mut [u8]) -> Result<(usize, IPAddress, u16), UdpError> {
+        let packet_len = self.parse_packet().ok_or(UdpError::WouldBlock)?;
+        let n = self.read(buf);
+        if packet_len > buf.len() {
+            self.flush();
+            return Err(UdpError::Truncated { packet_len });
+        }
+        Ok((n, self.remote_ip(), self.remote_port()))
+    }
+}
+
+impl Drop for EthernetUDP<'_> {
+    fn drop(&mut self) {
+        unsafe { raw::EthernetUDP_stop(self.this()) }
+    }
+}
+
+/// An [`EthernetUDP`] that has joined a multicast group.  Dropping it closes the socket, which
+/// makes the W5x00 leave the group.
+///
+/// To create one of these, use [`EthernetWrapper::new_udp_multicast`](crate::EthernetWrapper::new_udp_multicast)
+pub struct MulticastUdp<'a> {
+    pub(crate) inner: EthernetUDP<'a>,
+}
+
+impl<'a> Deref for MulticastUdp<'a> {
+    type Target = EthernetUDP<'a>;
+
+    fn deref(&self) -> &EthernetUDP<'a> {
+        &self.inner
+    }
+}
+
+impl<'a> DerefMut for MulticastUdp<'a> {
+    fn deref_mut(&mut self) -> &mut EthernetUDP<'a> {
+        &mut self.inner
+    }
+}
@@ -2,6 +2,7 @@
 
 mod raw;
 
+use core::marker::PhantomData;
 use cstr_core::CStr;
 use ethernet::{EthernetClient, IPAddress};
 use raw::PubSubClient;
@@ -34,8 +35,8 @@ pub type PubSubClientCallbackType = unsafe extern "C" fn(
 ///
 /// fn main() ->! {
 ///     use ethernet::ip_address_4;
-///     let mut client = ethernet.unwrap().make_client();
-///     let mqtt = PubSubClientWrapper::new(ip_address_4(192, 168,8,9), 1883, Some(mqtt_message_received), &client);
+///     let mut client = ethernet.unwrap().make_client().unwrap();
+///     let mqtt = PubSubClientWrapper::new(ip_address_4(192, 168,8,9), 1883, Some(mqtt_message_received), &mut client);
 ///     let val = mqtt.connect(cstr!("arduino"), None, None, None, 0, false, None, true);
 ///     let _ = mqtt.subscribe(cstr!("/arduino/writeln"), false);
 ///
@@ -48,11 +49,14 @@ pub type PubSubClientCallbackType = unsafe extern "C" fn(
 ///     }
 /// }
 ///```
-pub struct PubSubClientWrapper {
+///
+/// The C++ `PubSubClient` keeps a pointer to the `EthernetClient`, so the wrapper borrows it for `'c`.
+pub struct PubSubClientWrapper<'c> {
     inner: raw::PubSubClient,
+    _client: PhantomData<&'c mut ()>,
 }
 
-impl PubSubClientWrapper {
+impl<'c> PubSubClientWrapper<'c> {
     /// create a new MQTT connection to the broker at `host:port` using the `EthernetClient` for connectivity.
     /// The default port for MQTT is 1883
     /// Any messages we receive due to subscriptions will be passed to `callback` (yes, it is ugly, because the underlying implementation is simplistic).
@@ -60,12 +64,13 @@ impl PubSubClientWrapper {
         host: IPAddress,
         port: u16,
         callback: ::core::option::Option<PubSubClientCallbackType>,
-        client: &mut EthernetClient,
-    ) -> PubSubClientWrapper {
+        client: &'c mut EthernetClient<'_>,
+    ) -> PubSubClientWrapper<'c> {
         PubSubClientWrapper {
             inner: unsafe {
                 PubSubClient::new4(host, port, callback, client.as_client_mut_pointer())
             },
+            _client: PhantomData,
         }
     }
 
@@ -158,7 +163,7 @@ fn pointer_for(x: Option<&CStr>) -> *const i8 {
     }
 }
 
-impl Drop for PubSubClientWrapper {
+impl Drop for PubSubClientWrapper<'_> {
     fn drop(&mut self) {
         unsafe { self.inner.destruct() }
     }